@@ -2,7 +2,7 @@ pub mod strategy;
 pub mod position_type;
 
 use strategy::{choose_search_strategy, choose_evaluation_strategy};
-use types::{Board, Color, Square};
+use types::{Board, ChessMove, Color};
 use search::transposition::TranspositionTable;
 
 pub struct Arbiter {
@@ -22,10 +22,12 @@ impl Arbiter {
         }
     }
 
-    pub fn search_best_move(&mut self, board: &mut Board, max_depth: i32, color: Color) -> (Square, Square) {
+    /// Picks a move for `color`. `history` holds the hashes of the positions played before this one,
+    /// oldest first, so the search can see repetitions coming.
+    pub fn search_best_move(&mut self, board: &mut Board, max_depth: i32, color: Color, history: &[u64]) -> ChessMove {
         let position_type = position_type::determine_position_type(board, color);
         let search_strategy = choose_search_strategy(position_type);
-        search_strategy(board, max_depth, color, &mut self.transposition_table)
+        search_strategy(board, max_depth, color, &mut history.to_vec(), &mut self.transposition_table)
     }
 
     pub fn evaluate_position(&self, board: &Board, color: Color) -> i32 {
@@ -1,10 +1,10 @@
-use types::{Board, Color, Square};
+use types::{Board, ChessMove, Color};
 use search::{Search};
 use eval::{Evaluation};
 use crate::position_type::PositionType;
 use search::transposition::TranspositionTable;
 
-pub fn choose_search_strategy(position_type: PositionType) -> fn(&mut Board, i32, Color, &mut TranspositionTable) -> (Square, Square) {
+pub fn choose_search_strategy(position_type: PositionType) -> fn(&mut Board, i32, Color, &mut Vec<u64>, &mut TranspositionTable) -> ChessMove {
     match position_type {
         PositionType::Open | PositionType::SemiOpen => iterative_deepening_strategy,
         PositionType::Closed | PositionType::SemiClosed => lmr_strategy,
@@ -14,15 +14,15 @@ pub fn choose_search_strategy(position_type: PositionType) -> fn(&mut Board, i32
     }
 }
 
-fn iterative_deepening_strategy(board: &mut Board, max_depth: i32, color: Color, transposition_table: &mut TranspositionTable) -> (Square, Square) {
-    Search::iterative_deepening(board, max_depth, color, transposition_table)
+fn iterative_deepening_strategy(board: &mut Board, max_depth: i32, color: Color, history: &mut Vec<u64>, transposition_table: &mut TranspositionTable) -> ChessMove {
+    Search::iterative_deepening(board, max_depth, color, history, transposition_table)
 }
 
-fn negamax_strategy(board: &mut Board, max_depth: i32, color: Color, transposition_table: &mut TranspositionTable) -> (Square, Square) {
-    Search::best_move(board, max_depth, color, transposition_table)
+fn negamax_strategy(board: &mut Board, max_depth: i32, color: Color, history: &mut Vec<u64>, transposition_table: &mut TranspositionTable) -> ChessMove {
+    Search::best_move(board, max_depth, color, history, transposition_table)
 }
 
-fn lmr_strategy(board: &mut Board, max_depth: i32, color: Color, transposition_table: &mut TranspositionTable) -> (Square, Square) {
+fn lmr_strategy(board: &mut Board, max_depth: i32, color: Color, history: &mut Vec<u64>, transposition_table: &mut TranspositionTable) -> ChessMove {
     let mut best_move = None;
     let mut best_score = i32::MIN;
     let mut alpha = i32::MIN;
@@ -30,11 +30,15 @@ fn lmr_strategy(board: &mut Board, max_depth: i32, color: Color, transposition_t
 
     let legal_moves = board.generate_legal_moves(color);
     for m in legal_moves {
-        let piece = board.get_piece_at_square(m.0).expect("No piece found at source square").0;
-
-        board.make_move(m.0, m.1, piece, color);
-        let score = -Search::late_move_reductions(board, max_depth - 1, -beta, -alpha, color.opponent(), transposition_table);
-        board.unmake_move(m.0, m.1, piece, color);
+        history.push(board.hash());
+        let undo = board.make_move(m);
+        let score = if Search::is_draw(board, history) {
+            0
+        } else {
+            -Search::late_move_reductions(board, max_depth - 1, -beta, -alpha, color.opponent(), history, transposition_table)
+        };
+        board.unmake_move(m, undo);
+        history.pop();
 
         if score > best_score {
             best_score = score;
@@ -3,5 +3,5 @@ pub mod uci;
 pub mod xboard;
 
 pub use protocol::Protocol;
-pub use uci::Uci;
+pub use uci::{Uci, UciEngine, UciOptions};
 pub use xboard::XBoard;
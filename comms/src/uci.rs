@@ -1,5 +1,12 @@
+use std::io::{self, BufRead, Write};
+use arbiter::Arbiter;
+use search::{BookManager, BookOptions};
+use types::{Board, Variant};
 use crate::protocol::Protocol;
 
+/// How deep `go` searches when the GUI gives no `depth`.
+const DEFAULT_DEPTH: i32 = 5;
+
 pub struct Uci;
 
 impl Protocol for Uci {
@@ -13,3 +20,252 @@ impl Protocol for Uci {
         stdin.lock().lines().next().unwrap().unwrap()
     }
 }
+
+/// The engine options a UCI GUI can change with `setoption`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UciOptions {
+    /// Whether positions and moves use Chess960 castling, with the king taking its own rook.
+    pub chess960: bool,
+    pub book: BookOptions,
+}
+
+impl UciOptions {
+    /// The `option` lines announced after `id`, before `uciok`.
+    pub fn declarations(&self) -> Vec<String> {
+        let book = &self.book;
+        let files = book.books.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(";");
+        vec![
+            format!("option name UCI_Chess960 type check default {}", self.chess960),
+            format!("option name OwnBook type check default {}", book.enabled),
+            format!("option name BookFiles type string default {}", if files.is_empty() { "<empty>" } else { &files }),
+            format!("option name BookMaxPly type spin default {} min 0 max 1000", book.max_ply),
+            format!("option name BookMinWeight type spin default {} min 0 max {}", book.min_weight, u16::MAX),
+            format!("option name BookVariety type combo default {} var best var weighted var random", book.policy),
+            format!("option name BookSeed type spin default {} min 0 max {}", book.seed.unwrap_or(0), i32::MAX),
+        ]
+    }
+
+    /// Applies one `setoption name <name> value <value>`. Book options are handed to `BookOptions::set_option`.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "UCI_Chess960" => self.chess960 = value.trim().eq_ignore_ascii_case("true"),
+            _ => self.book.set_option(name, value)?,
+        }
+        Ok(())
+    }
+}
+
+/// The engine side of a UCI session: the position the GUI set up and the options it changed.
+pub struct UciEngine {
+    options: UciOptions,
+    board: Board,
+    history: Vec<u64>, // Hashes of every position before the current one, for repetitions
+    engine: Arbiter,
+    books: Option<BookManager>, // Opened on first use, and again whenever the book options change
+}
+
+impl Default for UciEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UciEngine {
+    pub fn new() -> Self {
+        UciEngine { options: UciOptions::default(), board: Board::new(), history: Vec::new(), engine: Arbiter::new(), books: None }
+    }
+
+    #[inline(always)]
+    pub fn options(&self) -> &UciOptions {
+        &self.options
+    }
+
+    #[inline(always)]
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Answers commands until `quit` or the end of the input.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        for line in input.lines() {
+            if !self.handle(&line?, output)? {
+                break;
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Answers one command; false once the GUI has asked the engine to quit. Unknown commands are ignored, as UCI asks.
+    pub fn handle<W: Write>(&mut self, line: &str, output: &mut W) -> io::Result<bool> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => {
+                writeln!(output, "id name Kishmat")?;
+                writeln!(output, "id author Ahmad Hamdi Emara")?;
+                for declaration in self.options.declarations() {
+                    writeln!(output, "{}", declaration)?;
+                }
+                writeln!(output, "uciok")?;
+            }
+            Some("isready") => {
+                self.open_books(output)?;
+                writeln!(output, "readyok")?;
+            }
+            Some("setoption") => self.set_option(&tokens[1..], output)?,
+            Some("ucinewgame") => {
+                self.engine = Arbiter::new();
+                self.set_position(&["startpos"], output)?;
+            }
+            Some("position") => self.set_position(&tokens[1..], output)?,
+            Some("go") => self.go(&tokens[1..], output)?,
+            Some("quit") => return Ok(false),
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// `setoption name <name> [value <value>]`; both the name and the value may contain spaces.
+    fn set_option<W: Write>(&mut self, tokens: &[&str], output: &mut W) -> io::Result<()> {
+        let value_at = tokens.iter().position(|&token| token == "value").unwrap_or(tokens.len());
+        let name = tokens[..value_at].iter().skip_while(|&&token| token == "name").copied().collect::<Vec<_>>().join(" ");
+        let value = tokens.get(value_at + 1..).unwrap_or_default().join(" ");
+
+        if let Err(error) = self.options.set_option(&name, &value) {
+            writeln!(output, "info string {}", error)?;
+        }
+        self.board.set_chess960(self.options.chess960);
+        Ok(())
+    }
+
+    /// `position (startpos | fen <fen>) [moves <move>...]`. A move that is not legal stops the replay there.
+    fn set_position<W: Write>(&mut self, tokens: &[&str], output: &mut W) -> io::Result<()> {
+        let moves_at = tokens.iter().position(|&token| token == "moves").unwrap_or(tokens.len());
+        let board = match tokens.first().copied() {
+            Some("startpos") => Ok(Board::new()),
+            Some("fen") => Board::from_fen(&tokens[1..moves_at].join(" ")).map_err(|error| error.to_string()),
+            _ => Err("position expects startpos or fen".to_string()),
+        };
+        let mut board = match board {
+            Ok(board) => board,
+            Err(error) => return writeln!(output, "info string {}", error),
+        };
+        if self.options.chess960 {
+            board.set_chess960(true); // Shredder-FEN positions turn it on by themselves; X-FEN ones need telling
+        }
+
+        self.history.clear();
+        for text in tokens.iter().skip(moves_at + 1) {
+            let legal = board.generate_legal_moves(board.side_to_move);
+            let Some(mv) = board.parse_uci_move(text).filter(|mv| legal.contains(mv)) else {
+                writeln!(output, "info string Illegal move {}", text)?;
+                break;
+            };
+            self.history.push(board.hash());
+            board.apply_move(mv, board.side_to_move);
+        }
+        self.board = board;
+        Ok(())
+    }
+
+    /// `go [depth <plies>]`: answers with a book move when one of the books knows the position, else searches.
+    fn go<W: Write>(&mut self, tokens: &[&str], output: &mut W) -> io::Result<()> {
+        let depth = tokens
+            .iter()
+            .position(|&token| token == "depth")
+            .and_then(|index| tokens.get(index + 1)?.parse().ok())
+            .unwrap_or(DEFAULT_DEPTH);
+
+        if self.board.generate_legal_moves(self.board.side_to_move).is_empty() {
+            return writeln!(output, "bestmove 0000");
+        }
+
+        self.open_books(output)?;
+        let book_move = match self.books.as_mut() {
+            Some(books) if self.board.variant() == Variant::Standard => books.get_move(&self.board).map(|book_move| book_move.mv),
+            _ => None,
+        };
+        let color = self.board.side_to_move;
+        let best_move = match book_move {
+            Some(mv) => mv,
+            None => self.engine.search_best_move(&mut self.board, depth, color, &self.history),
+        };
+        writeln!(output, "bestmove {}", self.board.move_to_uci(best_move))
+    }
+
+    /// Opens the configured books unless they are already open with the current options.
+    fn open_books<W: Write>(&mut self, output: &mut W) -> io::Result<()> {
+        if self.books.as_ref().map(BookManager::options) == Some(&self.options.book) {
+            return Ok(());
+        }
+
+        match BookManager::new(self.options.book.clone()) {
+            Ok(books) => self.books = Some(books),
+            Err(error) => {
+                self.books = None;
+                writeln!(output, "info string Could not open the books: {}", error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(engine: &mut UciEngine, commands: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        engine.run(commands.as_bytes(), &mut output).expect("Writing to a buffer");
+        String::from_utf8(output).expect("UTF-8 output").lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_options_share_one_parser() {
+        let mut options = UciOptions::default();
+        options.set_option("UCI_Chess960", "true").expect("Valid option");
+        options.set_option("BookVariety", "random").expect("Valid option");
+        options.set_option("BookMaxPly", "12").expect("Valid option");
+
+        assert!(options.chess960);
+        assert_eq!(options.book.max_ply, 12);
+        assert!(options.set_option("BookMaxPly", "deep").is_err());
+        assert!(options.set_option("Hash", "64").is_err());
+        assert!(options.declarations().contains(&"option name BookVariety type combo default random var best var weighted var random".to_string()));
+    }
+
+    #[test]
+    fn test_uci_session() {
+        let mut engine = UciEngine::new();
+        let lines = session(&mut engine, "uci\nisready\nsetoption name OwnBook value false\nposition startpos moves e2e4 e7e5\ngo depth 2\nquit\nisready\n");
+
+        assert_eq!(lines.first().map(String::as_str), Some("id name Kishmat"));
+        assert!(lines.contains(&"option name UCI_Chess960 type check default false".to_string()));
+        assert!(lines.contains(&"uciok".to_string()));
+        assert_eq!(lines.iter().filter(|line| *line == "readyok").count(), 1); // Nothing is answered after quit
+
+        let best = lines.iter().find_map(|line| line.strip_prefix("bestmove ")).expect("A best move");
+        let mv = engine.board().parse_uci_move(best).expect("Valid UCI move");
+        assert!(engine.board().generate_legal_moves(engine.board().side_to_move).contains(&mv));
+    }
+
+    #[test]
+    fn test_chess960_castling_notation() {
+        let mut engine = UciEngine::new();
+        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+        session(&mut engine, &format!("position fen {}\n", fen));
+        let castle = engine.board().parse_uci_move("e1g1").expect("Valid UCI move");
+        assert_eq!(engine.board().move_to_uci(castle), "e1g1");
+
+        // Switching the option on mid-game switches the notation of the current position too
+        session(&mut engine, "setoption name UCI_Chess960 value true\n");
+        assert!(engine.options().chess960 && engine.board().is_chess960());
+        let castle = engine.board().parse_uci_move("e1h1").expect("Valid UCI move");
+        assert!(castle.is_castle());
+        assert_eq!(engine.board().move_to_uci(castle), "e1h1");
+
+        // Under Chess960 the GUI castles by taking the rook
+        session(&mut engine, &format!("position fen {} moves e1h1 e8a8\n", fen));
+        assert_eq!(engine.board().to_fen(), "2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 w - - 2 2");
+    }
+}
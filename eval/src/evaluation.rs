@@ -1,4 +1,4 @@
-use types::{clear_bit, count_bits, get_lsb, Board, Color, Piece, Square};
+use types::{clear_bit, count_bits, get_lsb, Board, Color, Piece, Square, Variant, KING_ATTACKS};
 
 #[derive(Copy, Clone, Default)]
 pub struct Evaluation {
@@ -41,15 +41,23 @@ impl Evaluation {
         const BISHOP_VALUE: i32 = 330;
         const ROOK_VALUE: i32 = 500;
         const QUEEN_VALUE: i32 = 900;
+        const ALFIL_VALUE: i32 = 120;
+        const FERZ_VALUE: i32 = 180;
+
+        // Shatranj's alfil and ferz take the bishop's and queen's places but move only a square or two
+        let (bishop_value, queen_value) = match board.variant() {
+            Variant::Standard => (BISHOP_VALUE, QUEEN_VALUE),
+            Variant::Shatranj => (ALFIL_VALUE, FERZ_VALUE),
+        };
 
         let mut material_score = 0;
 
         // Count pieces and add to material score
         material_score += PAWN_VALUE * board.piece_count(Piece::Pawn, color) as i32;
         material_score += KNIGHT_VALUE * board.piece_count(Piece::Knight, color) as i32;
-        material_score += BISHOP_VALUE * board.piece_count(Piece::Bishop, color) as i32;
+        material_score += bishop_value * board.piece_count(Piece::Bishop, color) as i32;
         material_score += ROOK_VALUE * board.piece_count(Piece::Rook, color) as i32;
-        material_score += QUEEN_VALUE * board.piece_count(Piece::Queen, color) as i32;
+        material_score += queen_value * board.piece_count(Piece::Queen, color) as i32;
 
         material_score
     }
@@ -101,9 +109,19 @@ impl Evaluation {
         let king_square = board.king_square(color);
         let pawn_shield = board.pawn_shield(color, king_square);
 
-        let king_safety = count_bits(pawn_shield) as i32 * 10;
-
         // Basic heuristic: more pawns near the king is generally better
+        let mut king_safety = count_bits(pawn_shield) as i32 * 10;
+
+        // Each square around the king the opponent already hits is a way in for an attack
+        let mut king_zone = KING_ATTACKS[king_square.to_index()];
+        while king_zone != 0 {
+            let square_index = get_lsb(king_zone);
+            if board.is_square_attacked(Square::from_index(square_index), color.opponent()) {
+                king_safety -= 8;
+            }
+            clear_bit(&mut king_zone, square_index);
+        }
+
         king_safety
     }
 
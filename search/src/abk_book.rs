@@ -0,0 +1,368 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use types::{Board, ChessMove, Color, Piece, Square};
+use crate::book::{Book, BookMove, MoveStats};
+
+const ENTRY_SIZE: usize = 28;
+/// Entries before this index hold the header; the first move from the starting position lives here.
+const ROOT_INDEX: usize = 900;
+const HEADER_SIZE: usize = ROOT_INDEX * ENTRY_SIZE;
+
+const COMMENT_OFFSET: usize = 0x0c;
+const AUTHOR_OFFSET: usize = 0x85;
+const SETTINGS_OFFSET: usize = 0xd6;
+
+/// The settings block Arena stores in the header of every `.abk` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AbkSettings {
+    pub comment: String,
+    pub author: String,
+    pub depth: i32,
+    pub moves: i32,
+    pub min_games: i32,
+    pub min_win: i32,
+    pub win_percent_white: i32,
+    pub win_percent_black: i32,
+    pub probability_priority: i32,
+    pub probability_games: i32,
+    pub probability_win_percent: i32,
+    pub use_book_half_moves: i32,
+}
+
+impl AbkSettings {
+    fn from_header(header: &[u8]) -> io::Result<Self> {
+        if header.len() < HEADER_SIZE || &header[1..3] != b"AB" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not an Arena ABK book"));
+        }
+
+        let int = |index: usize| read_i32(header, SETTINGS_OFFSET + 4 * index);
+        Ok(AbkSettings {
+            comment: read_pascal_string(header, COMMENT_OFFSET),
+            author: read_pascal_string(header, AUTHOR_OFFSET),
+            depth: int(0),
+            moves: int(1),
+            min_games: int(2),
+            min_win: int(3),
+            win_percent_white: int(4),
+            win_percent_black: int(5),
+            probability_priority: int(6),
+            probability_games: int(7),
+            probability_win_percent: int(8),
+            use_book_half_moves: int(9),
+        })
+    }
+}
+
+/// One node of the ABK move tree; children and siblings are entry indices, -1 meaning none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbkEntry {
+    pub from: u8,
+    pub to: u8,
+    pub promotion: i8,
+    pub priority: u8,
+    pub games: i32,
+    pub won: i32,
+    pub lost: i32,
+    pub ply: i32,
+    pub first_child: i32,
+    pub next_sibling: i32,
+}
+
+impl AbkEntry {
+    fn from_bytes(bytes: &[u8]) -> AbkEntry {
+        AbkEntry {
+            from: bytes[0],
+            to: bytes[1],
+            promotion: bytes[2] as i8,
+            priority: bytes[3],
+            games: read_i32(bytes, 4),
+            won: read_i32(bytes, 8),
+            lost: read_i32(bytes, 12),
+            ply: read_i32(bytes, 16),
+            first_child: read_i32(bytes, 20),
+            next_sibling: read_i32(bytes, 24),
+        }
+    }
+
+    /// Decodes the move against the position it is played from.
+    pub fn to_move(&self, board: &Board) -> Option<ChessMove> {
+        if self.from > 63 || self.to > 63 {
+            return None;
+        }
+
+        // Promotions are signed by color; only the magnitude names the piece
+        let promotion = match self.promotion.unsigned_abs() {
+            0 => None,
+            1 => Some(Piece::Rook),
+            2 => Some(Piece::Knight),
+            3 => Some(Piece::Bishop),
+            4 => Some(Piece::Queen),
+            _ => return None,
+        };
+
+        Some(board.move_from_squares(Square::from_index(self.from as usize), Square::from_index(self.to as usize), promotion))
+    }
+
+    pub fn stats(&self) -> MoveStats {
+        MoveStats {
+            games: self.games.max(0) as u32,
+            wins: self.won.max(0) as u32,
+            losses: self.lost.max(0) as u32,
+            priority: self.priority,
+        }
+    }
+
+    /// Whether both entries encode the same move.
+    #[inline(always)]
+    fn same_move(&self, other: &AbkEntry) -> bool {
+        (self.from, self.to, self.promotion) == (other.from, other.to, other.promotion)
+    }
+
+    /// Folds in the statistics of the same move reached through a transposition.
+    fn merge(&mut self, other: &AbkEntry) {
+        self.games = self.games.saturating_add(other.games);
+        self.won = self.won.saturating_add(other.won);
+        self.lost = self.lost.saturating_add(other.lost);
+        self.priority = self.priority.max(other.priority);
+    }
+
+    #[inline(always)]
+    fn link(index: i32) -> Option<usize> {
+        usize::try_from(index).ok()
+    }
+}
+
+/// An Arena opening book. The file is a move tree, so it is replayed once on load and indexed by Polyglot key.
+#[derive(Debug, Clone)]
+pub struct AbkBook {
+    settings: AbkSettings,
+    entries: Vec<AbkEntry>,
+    positions: HashMap<u64, Vec<AbkEntry>>, // Position key -> the moves played from it, transpositions merged
+}
+
+impl AbkBook {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let settings = AbkSettings::from_header(bytes)?;
+        let entries: Vec<AbkEntry> = bytes.chunks_exact(ENTRY_SIZE).map(AbkEntry::from_bytes).collect();
+
+        let mut book = AbkBook { settings, entries, positions: HashMap::new() };
+        book.index_tree();
+        Ok(book)
+    }
+
+    #[inline(always)]
+    pub fn settings(&self) -> &AbkSettings {
+        &self.settings
+    }
+
+    #[inline(always)]
+    pub fn entries(&self) -> &[AbkEntry] {
+        &self.entries
+    }
+
+    /// Walks the tree from the root, recording which moves are playable from each position reached.
+    /// A position reached by several move orders gets one entry per move, with their statistics summed.
+    fn index_tree(&mut self) {
+        let mut visited = vec![false; self.entries.len()];
+        let mut pending = vec![(ROOT_INDEX, Board::new())];
+
+        while let Some((first, board)) = pending.pop() {
+            let mut next = Some(first);
+
+            while let Some(index) = next.filter(|&index| index < self.entries.len() && !visited[index]) {
+                visited[index] = true; // Guards against corrupt files whose links loop
+                let entry = self.entries[index];
+                next = AbkEntry::link(entry.next_sibling);
+
+                let Some(mv) = entry.to_move(&board).filter(|&mv| board.is_legal_move(mv, board.side_to_move)) else {
+                    continue; // Skip moves that do not fit the position, and everything below them
+                };
+                let moves = self.positions.entry(board.polyglot_key()).or_default();
+                match moves.iter_mut().find(|known| known.same_move(&entry)) {
+                    Some(known) => known.merge(&entry),
+                    None => moves.push(entry),
+                }
+
+                if let Some(child) = AbkEntry::link(entry.first_child) {
+                    let mut child_board = board;
+                    child_board.make_move(mv);
+                    pending.push((child, child_board));
+                }
+            }
+        }
+    }
+
+    /// Whether a move passes the book's minimum games and win percentage settings.
+    fn passes_filters(&self, stats: &MoveStats, color: Color) -> bool {
+        let min_percent = match color {
+            Color::White => self.settings.win_percent_white,
+            Color::Black => self.settings.win_percent_black,
+        }
+        .max(self.settings.min_win);
+
+        stats.games as i32 >= self.settings.min_games
+            && (stats.games == 0 || stats.score_percent() >= min_percent as f64)
+    }
+
+    /// Blends priority, game count and score into one weight, using the book's probability settings as mix ratios.
+    fn weigh(&self, candidates: &[MoveStats]) -> Vec<u16> {
+        let max_priority = candidates.iter().map(|stats| stats.priority).max().unwrap_or(0).max(1) as f64;
+        let max_games = candidates.iter().map(|stats| stats.games).max().unwrap_or(0).max(1) as f64;
+
+        let mut mix = [
+            self.settings.probability_priority.max(0) as f64,
+            self.settings.probability_games.max(0) as f64,
+            self.settings.probability_win_percent.max(0) as f64,
+        ];
+        if mix.iter().all(|&ratio| ratio == 0.0) {
+            mix = [0.0, 1.0, 0.0]; // No preference configured: follow the games played
+        }
+        let total: f64 = mix.iter().sum();
+
+        candidates
+            .iter()
+            .map(|stats| {
+                let score = mix[0] * stats.priority as f64 / max_priority
+                    + mix[1] * stats.games as f64 / max_games
+                    + mix[2] * stats.score_percent() / 100.0;
+                (score / total * u16::MAX as f64).round() as u16
+            })
+            .collect()
+    }
+}
+
+impl Book for AbkBook {
+    fn moves(&self, board: &Board) -> Vec<BookMove> {
+        if board.ply() as i32 >= self.settings.use_book_half_moves {
+            return Vec::new();
+        }
+
+        let Some(entries) = self.positions.get(&board.polyglot_key()) else {
+            return Vec::new();
+        };
+
+        let candidates: Vec<(ChessMove, MoveStats)> = entries
+            .iter()
+            .filter_map(|entry| Some((entry.to_move(board)?, entry.stats())))
+            .filter(|(mv, stats)| board.is_legal_move(*mv, board.side_to_move) && self.passes_filters(stats, board.side_to_move))
+            .collect();
+
+        let weights = self.weigh(&candidates.iter().map(|(_, stats)| *stats).collect::<Vec<_>>());
+        let mut moves: Vec<BookMove> = candidates
+            .into_iter()
+            .zip(weights)
+            .map(|((mv, stats), weight)| BookMove { mv, weight, learn: 0, stats: Some(stats) })
+            .collect();
+
+        moves.sort_by_key(|book_move| Reverse(book_move.weight));
+        moves
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len().saturating_sub(ROOT_INDEX)
+    }
+}
+
+#[inline(always)]
+fn read_i32(bytes: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(bytes[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+/// Reads a length-prefixed (Delphi short) string.
+fn read_pascal_string(bytes: &[u8], offset: usize) -> String {
+    let length = bytes[offset] as usize;
+    let end = (offset + 1 + length).min(bytes.len());
+    String::from_utf8_lossy(&bytes[offset + 1..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shipped_book() -> AbkBook {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/books/ABK/Perfect2023.abk");
+        AbkBook::load(path).expect("Shipped ABK book")
+    }
+
+    #[test]
+    fn test_header_settings() {
+        let book = shipped_book();
+        let settings = book.settings();
+
+        assert_eq!(settings.author, "Sedat Canbaz");
+        assert_eq!(settings.depth, 28);
+        assert_eq!(settings.moves, 5318);
+        assert_eq!(settings.use_book_half_moves, 9999);
+        assert_eq!(book.len(), 5318);
+    }
+
+    #[test]
+    fn test_tree_is_indexed_by_position() {
+        let book = shipped_book();
+        let mut board = Board::new();
+
+        let root: Vec<String> = book.moves(&board).iter().map(|book_move| book_move.mv.to_string()).collect();
+        assert_eq!(root.len(), 4);
+        for expected in ["e2e4", "d2d4", "c2c4", "g1f3"] {
+            assert!(root.contains(&expected.to_string()), "missing {}", expected);
+        }
+
+        // One ply deeper the replies to 1.e4 come from the first child's sibling chain
+        board.make_move(board.parse_uci_move("e2e4").expect("Valid UCI move"));
+        let replies = book.moves(&board);
+        assert!(replies.iter().any(|book_move| book_move.mv.to_string() == "c7c5"));
+        assert!(replies.iter().all(|book_move| book_move.stats.is_some()));
+    }
+
+    /// From, to, priority, games, won, lost, first child and next sibling of one entry.
+    type Row = (u8, u8, u8, i32, i32, i32, i32, i32);
+
+    /// Builds a book whose entries start at the root.
+    fn synthetic_book(rows: &[Row]) -> AbkBook {
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        bytes[1..3].copy_from_slice(b"AB");
+        bytes[SETTINGS_OFFSET + 36..SETTINGS_OFFSET + 40].copy_from_slice(&9999i32.to_le_bytes()); // Use book half moves
+
+        for &(from, to, priority, games, won, lost, child, sibling) in rows {
+            bytes.extend_from_slice(&[from, to, 0, priority]);
+            for value in [games, won, lost, 0, child, sibling] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        AbkBook::from_bytes(&bytes).expect("Valid synthetic book")
+    }
+
+    #[test]
+    fn test_transpositions_are_merged() {
+        // 1.d4 Nf6 2.c4 and 1.c4 Nf6 2.d4 reach the same position, each with its own replies below it
+        let book = synthetic_book(&[
+            (11, 27, 0, 1, 0, 0, 901, 902), // 900: 1.d4
+            (62, 45, 0, 1, 0, 0, 903, -1),  // 901: 1...Nf6
+            (10, 26, 0, 1, 0, 0, 904, -1),  // 902: 1.c4
+            (10, 26, 0, 1, 0, 0, 905, -1),  // 903: 2.c4
+            (62, 45, 0, 1, 0, 0, 906, -1),  // 904: 1...Nf6
+            (52, 44, 5, 10, 3, 2, -1, -1),  // 905: 2...e6
+            (11, 27, 0, 1, 0, 0, 907, -1),  // 906: 2.d4
+            (52, 44, 9, 4, 1, 1, -1, 908),  // 907: 2...e6
+            (54, 46, 0, 2, 1, 1, -1, -1),   // 908: 2...g6
+        ]);
+
+        let mut board = Board::new();
+        for uci in ["d2d4", "g8f6", "c2c4"] {
+            board.make_move(board.parse_uci_move(uci).expect("Valid UCI move"));
+        }
+
+        let moves = book.moves(&board);
+        assert_eq!(moves.len(), 2);
+        let e6 = moves.iter().find(|book_move| book_move.mv.to_string() == "e7e6").expect("e6 is in the book");
+        assert_eq!(e6.stats, Some(MoveStats { games: 14, wins: 4, losses: 3, priority: 9 }));
+        assert!(moves.iter().any(|book_move| book_move.mv.to_string() == "g7g6"));
+    }
+}
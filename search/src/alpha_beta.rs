@@ -1,5 +1,5 @@
 use crate::transposition::TranspositionTable;
-use types::{Board, Color, Square};
+use types::{Board, ChessMove, Color};
 use crate::Search;
 
 impl Search{
@@ -9,12 +9,13 @@ impl Search{
         alpha: i32,
         beta: i32,
         color: Color,
+        history: &mut Vec<u64>,
         transposition_table: &mut TranspositionTable,
-) -> (i32, Option<(Square, Square)>) {
+) -> (i32, Option<ChessMove>) {
     if depth == 0 {
-        return Self::negamax(board, depth, alpha, beta, color, transposition_table);
+        return Self::negamax(board, depth, alpha, beta, color, history, transposition_table);
     }
 
-    Self::negamax(board, depth, alpha, beta, color, transposition_table)
+    Self::negamax(board, depth, alpha, beta, color, history, transposition_table)
 }
 }
@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use types::{Board, ChessMove};
+use crate::abk_book::AbkBook;
+use crate::ctg_book::CtgBook;
+use crate::polyglot_book::PolyglotBook;
+
+/// Game statistics some book formats keep for each move, counted from the mover's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MoveStats {
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub priority: u8,
+}
+
+impl MoveStats {
+    #[inline(always)]
+    pub fn draws(&self) -> u32 {
+        self.games.saturating_sub(self.wins + self.losses)
+    }
+
+    /// Score percentage for the side playing the move, counting draws as half a point.
+    pub fn score_percent(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + self.draws() as f64 / 2.0) * 100.0 / self.games as f64
+    }
+}
+
+/// A candidate move from a book together with its weight and whatever extra data the format stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub mv: ChessMove,
+    pub weight: u16,
+    pub learn: u32,
+    pub stats: Option<MoveStats>,
+}
+
+/// Common interface of every opening book format.
+pub trait Book: fmt::Debug + Send {
+    /// Legal book moves for the position, most heavily weighted first.
+    fn moves(&self, board: &Board) -> Vec<BookMove>;
+
+    /// Number of stored entries (moves), for reporting.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Opens a book, choosing the reader from the file extension (`.bin` Polyglot, `.abk` Arena, `.ctg` ChessBase).
+pub fn load_book<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Book>> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+
+    match extension.as_deref() {
+        Some("bin") => Ok(Box::new(PolyglotBook::load(path)?)),
+        Some("abk") => Ok(Box::new(AbkBook::load(path)?)),
+        Some("ctg" | "cto" | "ctb") => Ok(Box::new(CtgBook::load(path)?)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unsupported opening book format: {}", path.display()),
+        )),
+    }
+}
+
+/// Visits every book move reachable from `start` within `max_ply` half-moves, depth first.
+///
+/// Each position is expanded once, so lines that transpose into an already visited position stop there.
+/// The visitor gets the position, the moves leading to it from `start` and the book move played from it.
+pub fn walk_book(book: &dyn Book, start: &Board, max_ply: u32, mut visit: impl FnMut(&Board, &[ChessMove], &BookMove)) {
+    let mut expanded = HashSet::new();
+    let mut line = Vec::new();
+    walk_from(book, start, max_ply, &mut line, &mut expanded, &mut visit);
+}
+
+fn walk_from(
+    book: &dyn Book,
+    board: &Board,
+    plies_left: u32,
+    line: &mut Vec<ChessMove>,
+    expanded: &mut HashSet<u64>,
+    visit: &mut impl FnMut(&Board, &[ChessMove], &BookMove),
+) {
+    if plies_left == 0 || !expanded.insert(board.polyglot_key()) {
+        return;
+    }
+
+    for book_move in book.moves(board) {
+        visit(board, line, &book_move);
+
+        let mut child = *board;
+        child.make_move(book_move.mv);
+        line.push(book_move.mv);
+        walk_from(book, &child, plies_left - 1, line, expanded, visit);
+        line.pop();
+    }
+}
+
+/// A book move as the explorer shows it: in SAN, with its share of the position's total weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbedMove {
+    pub san: String,
+    pub book_move: BookMove,
+    pub percent: f64,
+}
+
+/// Every book move for the position, most heavily weighted first, ready for display.
+pub fn probe(book: &dyn Book, board: &Board) -> Vec<ProbedMove> {
+    let moves = book.moves(board);
+    let total: u32 = moves.iter().map(|book_move| book_move.weight as u32).sum();
+
+    moves
+        .into_iter()
+        .map(|book_move| ProbedMove {
+            san: board.move_to_san(book_move.mv),
+            percent: if total == 0 { 0.0 } else { book_move.weight as f64 * 100.0 / total as f64 },
+            book_move,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polyglot_book::PolyglotEntry;
+
+    /// A Polyglot book holding the given (UCI line, move, weight) entries.
+    fn book(entries: &[(&[&str], &str, u16)]) -> PolyglotBook {
+        let entries = entries
+            .iter()
+            .map(|&(line, uci, weight)| {
+                let mut board = Board::new();
+                for played in line {
+                    board.make_move(board.parse_uci_move(played).expect("Valid UCI move"));
+                }
+                let mv = board.parse_uci_move(uci).expect("Valid UCI move");
+                PolyglotEntry { key: board.polyglot_key(), raw_move: mv.to_polyglot(), weight, learn: 0 }
+            })
+            .collect();
+        PolyglotBook::from_entries(entries)
+    }
+
+    #[test]
+    fn test_probe_and_walk() {
+        let book = book(&[
+            (&[], "e2e4", 3),
+            (&[], "g1f3", 1),
+            (&["e2e4"], "e7e5", 1),
+            (&["g1f3"], "g8f6", 1),
+            (&["g1f3", "g8f6"], "e2e4", 1),
+        ]);
+
+        let probed = probe(&book, &Board::new());
+        let listed: Vec<(&str, f64)> = probed.iter().map(|probed| (probed.san.as_str(), probed.percent)).collect();
+        assert_eq!(listed, vec![("e4", 75.0), ("Nf3", 25.0)]);
+
+        let mut lines = Vec::new();
+        walk_book(&book, &Board::new(), 2, |_, line, book_move| lines.push((line.len(), book_move.mv.to_string())));
+        assert_eq!(lines, vec![(0, "e2e4".to_string()), (1, "e7e5".to_string()), (0, "g1f3".to_string()), (1, "g8f6".to_string())]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use types::{Color, Game};
+use crate::polyglot_book::{PolyglotBook, PolyglotEntry};
+
+/// Settings for turning a game collection into a Polyglot book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookBuildOptions {
+    /// Moves beyond this many half-moves into a game are not recorded.
+    pub max_ply: u32,
+    /// A move must have been played in at least this many games to be kept.
+    pub min_games: u32,
+    /// Games are skipped unless both players are rated at least this much (0 accepts unrated games).
+    pub min_elo: u32,
+    /// Points credited to a move for each game its side went on to win, draw or lose.
+    pub win_score: u32,
+    pub draw_score: u32,
+    pub loss_score: u32,
+}
+
+impl Default for BookBuildOptions {
+    fn default() -> Self {
+        BookBuildOptions { max_ply: 20, min_games: 1, min_elo: 0, win_score: 2, draw_score: 1, loss_score: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MoveTally {
+    games: u32,
+    score: u64,
+}
+
+/// Replays games and tallies how often, and how well, each move was played from each position.
+#[derive(Debug, Default)]
+pub struct BookBuilder {
+    options: BookBuildOptions,
+    tallies: HashMap<(u64, u16), MoveTally>, // (Polyglot key, Polyglot move) -> results
+    games_used: usize,
+    games_skipped: usize,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookBuildOptions) -> Self {
+        BookBuilder { options, ..Self::default() }
+    }
+
+    #[inline(always)]
+    pub fn games_used(&self) -> usize {
+        self.games_used
+    }
+
+    #[inline(always)]
+    pub fn games_skipped(&self) -> usize {
+        self.games_skipped
+    }
+
+    /// Adds every game of a PGN file; games that cannot be read count as skipped.
+    pub fn add_pgn(&mut self, text: &str) {
+        self.add_games(Game::parse_all(text));
+    }
+
+    /// Adds games as a reader such as `PgnDatabase` hands them out, counting the unreadable ones as skipped.
+    pub fn add_games<E>(&mut self, games: impl IntoIterator<Item = Result<Game, E>>) {
+        for game in games {
+            match game {
+                Ok(game) => {
+                    self.add_game(&game);
+                }
+                Err(_) => self.games_skipped += 1,
+            }
+        }
+    }
+
+    /// Replays one game into the tallies; returns false when the game was filtered out.
+    pub fn add_game(&mut self, game: &Game) -> bool {
+        let accepted = self.tally_game(game);
+        if accepted {
+            self.games_used += 1;
+        } else {
+            self.games_skipped += 1;
+        }
+        accepted
+    }
+
+    fn tally_game(&mut self, game: &Game) -> bool {
+        let winner = match game.result() {
+            Some("1-0") => Some(Color::White),
+            Some("0-1") => Some(Color::Black),
+            Some("1/2-1/2") => None,
+            _ => return false, // Unfinished games say nothing about the moves
+        };
+
+        let rated = |tag| game.header(tag).and_then(|elo| elo.parse::<u32>().ok()).unwrap_or(0) >= self.options.min_elo;
+        if !rated("WhiteElo") || !rated("BlackElo") {
+            return false;
+        }
+
+        let mut board = game.start;
+        let mut played = Vec::new();
+        for mv in game.moves().take(self.options.max_ply as usize) {
+            played.push((board.polyglot_key(), mv.to_polyglot(), board.side_to_move));
+            board.make_move(mv);
+        }
+
+        for (key, raw_move, mover) in played {
+            let score = match winner {
+                Some(color) if color == mover => self.options.win_score,
+                Some(_) => self.options.loss_score,
+                None => self.options.draw_score,
+            };
+            let tally = self.tallies.entry((key, raw_move)).or_default();
+            tally.games += 1;
+            tally.score += score as u64;
+        }
+        true
+    }
+
+    /// Keeps the moves played often enough and weights them by score, scaled down if needed to fit 16 bits.
+    ///
+    /// Moves that never scored a point get no weight and are left out.
+    pub fn build(&self) -> PolyglotBook {
+        let kept: Vec<(&(u64, u16), &MoveTally)> = self
+            .tallies
+            .iter()
+            .filter(|(_, tally)| tally.games >= self.options.min_games && tally.score > 0)
+            .collect();
+
+        let max_score = kept.iter().map(|(_, tally)| tally.score).max().unwrap_or(0);
+        let scale = |score: u64| {
+            if max_score <= u16::MAX as u64 {
+                score as u16
+            } else {
+                (score * u16::MAX as u64 / max_score).max(1) as u16
+            }
+        };
+
+        let entries = kept
+            .into_iter()
+            .map(|(&(key, raw_move), tally)| PolyglotEntry { key, raw_move, weight: scale(tally.score), learn: 0 })
+            .collect();
+        PolyglotBook::from_entries(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Board;
+    use crate::book::Book;
+
+    const GAMES: &str = r#"
+[Event "Club"]
+[White "A"]
+[Black "B"]
+[WhiteElo "2200"]
+[BlackElo "2100"]
+[Result "1-0"]
+
+1. e4 {King's pawn} e5 2. Nf3 (2. f4 exf4) Nc6 $1 3. Bb5 1-0
+
+[White "C"]
+[Black "D"]
+[WhiteElo "2300"]
+[BlackElo "2250"]
+[Result "1/2-1/2"]
+
+1.e4 c5 2.Nf3 d6 1/2-1/2
+
+[White "E"]
+[Black "F"]
+[WhiteElo "1500"]
+[BlackElo "2400"]
+[Result "0-1"]
+
+1. d4 d5 0-1
+
+[White "G"]
+[Black "H"]
+[Result "*"]
+
+1. c4 *
+"#;
+
+    fn names(book: &PolyglotBook, board: &Board) -> Vec<(String, u16)> {
+        book.moves(board).iter().map(|book_move| (book_move.mv.to_string(), book_move.weight)).collect()
+    }
+
+    #[test]
+    fn test_build_weights_moves_by_result() {
+        let mut builder = BookBuilder::new(BookBuildOptions::default());
+        builder.add_pgn(GAMES);
+        assert_eq!((builder.games_used(), builder.games_skipped()), (3, 1));
+
+        let book = builder.build();
+        let start = Board::new();
+        // 1.e4 won once and drew once; 1.d4 lost its only game and is dropped
+        assert_eq!(names(&book, &start), vec![("e2e4".to_string(), 3)]);
+
+        let mut board = start;
+        board.make_move(board.parse_uci_move("e2e4").expect("Valid UCI move"));
+        assert_eq!(names(&book, &board), vec![("c7c5".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_ply_games_and_elo_filters() {
+        let options = BookBuildOptions { max_ply: 1, min_games: 2, min_elo: 2200, ..BookBuildOptions::default() };
+        let mut builder = BookBuilder::new(options);
+        builder.add_pgn(GAMES);
+        assert_eq!(builder.games_used(), 1); // Only the 2300 v 2250 draw is rated high enough
+
+        let mut builder = BookBuilder::new(BookBuildOptions { min_elo: 0, ..options });
+        builder.add_pgn(GAMES);
+        let book = builder.build();
+        assert_eq!(book.len(), 1); // Only 1.e4 was played twice, and nothing past the first ply is kept
+    }
+}
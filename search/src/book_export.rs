@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use types::Board;
+use crate::book::{walk_book, Book, BookMove};
+use crate::polyglot_book::{PolyglotBook, PolyglotEntry};
+
+/// One visited book move, in the order `walk_book` reached it.
+struct Visit {
+    depth: usize, // Moves played since the walk's start
+    ply: u32, // Half-moves played in the game so far
+    san: String,
+    book_move: BookMove,
+}
+
+fn visits(book: &dyn Book, start: &Board, max_ply: u32) -> Vec<Visit> {
+    let mut visits = Vec::new();
+    walk_book(book, start, max_ply, |board, line, book_move| {
+        visits.push(Visit { depth: line.len(), ply: board.ply(), san: board.move_to_san(book_move.mv), book_move: *book_move });
+    });
+    visits
+}
+
+/// The move with its number, `1. e4` for White and `1... e5` for Black.
+fn numbered(ply: u32, san: &str) -> String {
+    let number = ply / 2 + 1;
+    if ply.is_multiple_of(2) {
+        format!("{}. {}", number, san)
+    } else {
+        format!("{}... {}", number, san)
+    }
+}
+
+/// Re-encodes any book as Polyglot by walking it from the starting position.
+pub fn to_polyglot(book: &dyn Book, max_ply: u32) -> PolyglotBook {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+
+    walk_book(book, &Board::new(), max_ply, |board, _, book_move| {
+        let entry = PolyglotEntry {
+            key: board.polyglot_key(),
+            raw_move: book_move.mv.to_polyglot(),
+            weight: book_move.weight,
+            learn: book_move.learn,
+        };
+        if seen.insert((entry.key, entry.raw_move)) {
+            entries.push(entry);
+        }
+    });
+
+    PolyglotBook::from_entries(entries)
+}
+
+/// Lists every line of the book in SAN, one per row, with each move's weight in a brace comment
+/// so the listing still reads as PGN movetext.
+pub fn to_lines(book: &dyn Book, max_ply: u32) -> String {
+    let visits = visits(book, &Board::new(), max_ply);
+    let mut text = String::new();
+    let mut line: Vec<String> = Vec::new();
+
+    for (index, visit) in visits.iter().enumerate() {
+        line.truncate(visit.depth);
+        let san = if visit.ply.is_multiple_of(2) { numbered(visit.ply, &visit.san) } else { visit.san.clone() };
+        line.push(format!("{} {{{}}}", san, visit.book_move.weight));
+
+        // A move is the end of a line unless the next visit continues from it
+        let is_leaf = visits.get(index + 1).is_none_or(|next| next.depth <= visit.depth);
+        if is_leaf {
+            writeln!(text, "{}", line.join(" ")).expect("Writing to a String cannot fail");
+        }
+    }
+
+    text
+}
+
+/// Renders the book from `start` as an indented move tree, with weights and any game statistics the format keeps.
+pub fn to_tree(book: &dyn Book, start: &Board, max_ply: u32) -> String {
+    let mut text = String::new();
+
+    for visit in visits(book, start, max_ply) {
+        let mut row = format!("{}{}  weight {}", "  ".repeat(visit.depth), numbered(visit.ply, &visit.san), visit.book_move.weight);
+        if let Some(stats) = visit.book_move.stats {
+            write!(row, ", {} games, {:.1}%", stats.games, stats.score_percent()).expect("Writing to a String cannot fail");
+        }
+        writeln!(text, "{}", row).expect("Writing to a String cannot fail");
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::abk_book::AbkBook;
+
+    fn key_after(moves: &[&str]) -> u64 {
+        let mut board = Board::new();
+        for uci in moves {
+            board.make_move(board.parse_uci_move(uci).expect("Valid UCI move"));
+        }
+        board.polyglot_key()
+    }
+
+    #[test]
+    fn test_abk_converts_to_polyglot_and_text() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/books/ABK/Perfect2023.abk");
+        let abk = AbkBook::load(path).expect("Shipped ABK book");
+
+        let polyglot = to_polyglot(&abk, 2);
+        assert_eq!(polyglot.moves(&Board::new()).len(), abk.moves(&Board::new()).len());
+        assert!(!polyglot.entries_for(key_after(&["e2e4"])).is_empty());
+        assert!(polyglot.entries_for(key_after(&["e2e4", "c7c5"])).is_empty()); // Beyond the ply limit
+
+        let lines = to_lines(&abk, 2);
+        assert!(lines.lines().any(|line| line.starts_with("1. e4 {") && line.contains("} c5 {")));
+        assert!(lines.lines().all(|line| line.matches('{').count() == 2));
+
+        let tree = to_tree(&abk, &Board::new(), 2);
+        assert!(tree.lines().any(|row| row.starts_with("1. e4  weight")));
+        assert!(tree.lines().any(|row| row.starts_with("  1... c5  weight") && row.contains("games")));
+    }
+}
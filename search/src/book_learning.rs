@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::book::BookMove;
+use crate::polyglot_book::{PolyglotBook, PolyglotEntry};
+
+/// How a finished game went for the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Outcome {
+    /// Half-points scored: 2 for a win, 1 for a draw.
+    #[inline(always)]
+    fn points(self) -> u32 {
+        match self {
+            Outcome::Win => 2,
+            Outcome::Draw => 1,
+            Outcome::Loss => 0,
+        }
+    }
+}
+
+/// Results of the book moves the engine has played, kept in a Polyglot-format sidecar next to the book.
+///
+/// Each record packs the games played in the high half of the `learn` field and the half-points
+/// scored in the low half, so the book file itself is never rewritten.
+#[derive(Debug, Clone, Default)]
+pub struct BookLearning {
+    path: Option<PathBuf>,
+    records: HashMap<(u64, u16), u32>, // (Polyglot key, Polyglot move) -> packed learn value
+}
+
+impl BookLearning {
+    /// Reads the sidecar, starting empty if it does not exist yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let records = match PolyglotBook::load(path) {
+            Ok(book) => book.entries().iter().map(|entry| ((entry.key, entry.raw_move), entry.learn)).collect(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error),
+        };
+
+        Ok(BookLearning { path: Some(path.to_path_buf()), records })
+    }
+
+    /// The sidecar path used for a book file.
+    pub fn sidecar_path<P: AsRef<Path>>(book_path: P) -> PathBuf {
+        let mut path = book_path.as_ref().as_os_str().to_owned();
+        path.push(".learn");
+        PathBuf::from(path)
+    }
+
+    /// Games played and half-points scored with the move.
+    pub fn record(&self, key: u64, raw_move: u16) -> (u32, u32) {
+        let learn = self.records.get(&(key, raw_move)).copied().unwrap_or(0);
+        (learn >> 16, learn & 0xFFFF)
+    }
+
+    /// Credits a game's outcome to a move, saturating rather than wrapping the counters.
+    pub fn update(&mut self, key: u64, raw_move: u16, outcome: Outcome) {
+        let (games, points) = self.record(key, raw_move);
+        let games = (games + 1).min(0xFFFF);
+        let points = (points + outcome.points()).min(0xFFFF);
+        self.records.insert((key, raw_move), games << 16 | points);
+    }
+
+    /// Scales a book move's weight by how it has scored, with one drawn game assumed up front so a
+    /// single result only nudges it. A move that keeps losing sinks towards zero weight.
+    pub fn adjust(&self, key: u64, book_move: &mut BookMove) {
+        let Some(&learn) = self.records.get(&(key, book_move.mv.to_polyglot())) else {
+            return;
+        };
+
+        let (games, points) = (learn >> 16, learn & 0xFFFF);
+        let weight = book_move.weight as u64 * (points as u64 + 2) / (games as u64 + 2);
+        book_move.weight = weight.min(u16::MAX as u64) as u16;
+        book_move.learn = learn;
+    }
+
+    /// Writes the records back to the sidecar they were loaded from.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let entries = self
+            .records
+            .iter()
+            .map(|(&(key, raw_move), &learn)| PolyglotEntry { key, raw_move, weight: 0, learn })
+            .collect();
+        PolyglotBook::from_entries(entries).save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Board, ChessMove, MoveFlag, Square};
+
+    #[test]
+    fn test_losing_moves_lose_weight_and_records_persist() {
+        let key = Board::new().polyglot_key();
+        let e4 = ChessMove::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        let path = std::env::temp_dir().join(format!("kishmat-learning-{}.bin.learn", std::process::id()));
+
+        let mut learning = BookLearning::load(&path).expect("Missing sidecar starts empty");
+        for _ in 0..3 {
+            learning.update(key, e4.to_polyglot(), Outcome::Loss);
+        }
+        learning.update(key, e4.to_polyglot(), Outcome::Draw);
+        learning.save().expect("Sidecar written");
+
+        let reloaded = BookLearning::load(&path).expect("Sidecar read back");
+        std::fs::remove_file(&path).expect("Sidecar removed");
+        assert_eq!(reloaded.record(key, e4.to_polyglot()), (4, 1));
+
+        let mut book_move = BookMove { mv: e4, weight: 600, learn: 0, stats: None };
+        reloaded.adjust(key, &mut book_move);
+        assert_eq!(book_move.weight, 300);
+        assert_eq!(book_move.learn, 4 << 16 | 1);
+
+        // Moves without a record keep their weight
+        let mut d4 = BookMove { mv: ChessMove::new(Square::D2, Square::D4, MoveFlag::DoublePawnPush), weight: 600, learn: 0, stats: None };
+        reloaded.adjust(key, &mut d4);
+        assert_eq!(d4.weight, 600);
+    }
+}
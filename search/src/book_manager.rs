@@ -0,0 +1,185 @@
+use std::io;
+use std::path::PathBuf;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use types::Board;
+use crate::book::BookMove;
+use crate::book_learning::Outcome;
+use crate::opening_book::{OpeningBook, SelectionPolicy};
+
+/// Everything that decides how the engine uses its opening books.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookOptions {
+    /// Whether books are consulted at all.
+    pub enabled: bool,
+    /// Books in priority order; a later book is only asked when every earlier one misses.
+    pub books: Vec<PathBuf>,
+    /// Books are not consulted once the game is this many half-moves old.
+    pub max_ply: u32,
+    /// Book moves weighted below this are ignored.
+    pub min_weight: u16,
+    pub policy: SelectionPolicy,
+    /// Fixes the random choices of the weighted and random policies.
+    pub seed: Option<u64>,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        BookOptions { enabled: true, books: Vec::new(), max_ply: 40, min_weight: 1, policy: SelectionPolicy::default(), seed: None }
+    }
+}
+
+impl BookOptions {
+    /// Applies one engine option the way a UCI `setoption name <name> value <value>` would.
+    ///
+    /// `BookFiles` takes the books separated by `;`, and a `BookSeed` of 0 means no fixed seed.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let number = |value: &str| value.trim().parse::<u64>().map_err(|_| format!("Option {} expects a number, got '{}'", name, value));
+
+        match name {
+            "OwnBook" => self.enabled = value.trim().eq_ignore_ascii_case("true"),
+            "BookFiles" => {
+                self.books = value.split(';').map(str::trim).filter(|path| !path.is_empty()).map(PathBuf::from).collect();
+            }
+            "BookMaxPly" => self.max_ply = number(value)?.min(u32::MAX as u64) as u32,
+            "BookMinWeight" => self.min_weight = number(value)?.min(u16::MAX as u64) as u16,
+            "BookVariety" => self.policy = value.parse()?,
+            "BookSeed" => self.seed = Some(number(value)?).filter(|&seed| seed != 0),
+            _ => return Err(format!("Unknown option '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Consults an ordered list of books of any format, falling back down the list when a position misses.
+#[derive(Debug)]
+pub struct BookManager {
+    books: Vec<OpeningBook>,
+    options: BookOptions,
+    rng: StdRng,
+}
+
+impl BookManager {
+    /// Opens every configured book; a disabled manager opens none.
+    pub fn new(options: BookOptions) -> io::Result<Self> {
+        let books = if options.enabled {
+            options.books.iter().map(OpeningBook::open).collect::<io::Result<Vec<_>>>()?
+        } else {
+            Vec::new()
+        };
+        let rng = options.seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64);
+
+        Ok(BookManager { books, options, rng })
+    }
+
+    #[inline(always)]
+    pub fn books(&self) -> &[OpeningBook] {
+        &self.books
+    }
+
+    #[inline(always)]
+    pub fn options(&self) -> &BookOptions {
+        &self.options
+    }
+
+    /// The first book that knows the position and its moves that pass the weight filter, best first.
+    fn lookup(&self, board: &Board) -> Option<(usize, Vec<BookMove>)> {
+        if board.ply() >= self.options.max_ply {
+            return None;
+        }
+
+        self.books.iter().enumerate().find_map(|(index, book)| {
+            let moves: Vec<BookMove> = book.get_moves(board).into_iter().filter(|book_move| book_move.weight >= self.options.min_weight).collect();
+            (!moves.is_empty()).then_some((index, moves))
+        })
+    }
+
+    /// Book moves for the position from the first book that has any.
+    pub fn get_moves(&self, board: &Board) -> Vec<BookMove> {
+        self.lookup(board).map(|(_, moves)| moves).unwrap_or_default()
+    }
+
+    /// Picks a move with the configured variety, remembering it in its book for `learn`.
+    pub fn get_move(&mut self, board: &Board) -> Option<BookMove> {
+        let (index, moves) = self.lookup(board)?;
+        let choice = self.options.policy.choose(&moves, &mut self.rng)?;
+        self.books[index].remember(board, choice.mv);
+        Some(choice)
+    }
+
+    /// Passes the finished game's outcome to every book that supplied a move.
+    pub fn learn(&mut self, outcome: Outcome) -> io::Result<()> {
+        for book in &mut self.books {
+            book.learn(outcome)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn asset(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/books").join(name)
+    }
+
+    #[test]
+    fn test_engine_options() {
+        let mut options = BookOptions::default();
+        options.set_option("BookFiles", "a.bin; b.abk;").expect("Valid option");
+        options.set_option("BookVariety", "Weighted").expect("Valid option");
+        options.set_option("BookSeed", "0").expect("Valid option");
+        options.set_option("BookMaxPly", "12").expect("Valid option");
+
+        assert_eq!(options.books, vec![PathBuf::from("a.bin"), PathBuf::from("b.abk")]);
+        assert_eq!(options.policy, SelectionPolicy::WeightedRandom);
+        assert_eq!((options.seed, options.max_ply), (None, 12));
+        assert!(options.set_option("BookMinWeight", "lots").is_err());
+        assert!(options.set_option("Hash", "64").is_err());
+    }
+
+    #[test]
+    fn test_fallback_ply_limit_and_min_weight() {
+        let options = BookOptions {
+            books: vec![asset("Perfect2023.bin"), asset("ABK/Perfect2023.abk")],
+            max_ply: 2,
+            ..BookOptions::default()
+        };
+        let manager = BookManager::new(options.clone()).expect("Shipped books");
+
+        // The Polyglot book answers first; the weight filter drops its weight-0 moves only
+        let start = Board::new();
+        assert_eq!(manager.get_moves(&start), manager.books()[0].get_moves(&start));
+
+        let mut board = start;
+        for uci in ["e2e4", "e7e5"] {
+            board.make_move(board.parse_uci_move(uci).expect("Valid UCI move"));
+        }
+        assert!(manager.get_moves(&board).is_empty()); // Past the ply limit
+
+        // A high minimum weight pushes the lookup past the first book's light moves
+        let manager = BookManager::new(BookOptions { min_weight: 65535, ..options }).expect("Shipped books");
+        let moves = manager.get_moves(&start);
+        assert!(moves.iter().all(|book_move| book_move.stats.is_some())); // Only the ABK book has a 65535 move
+    }
+
+    #[test]
+    fn test_seeded_variety_is_repeatable() {
+        let options = BookOptions {
+            books: vec![asset("Perfect2023.bin")],
+            policy: SelectionPolicy::Uniform,
+            seed: Some(9),
+            ..BookOptions::default()
+        };
+        let picks = || {
+            let mut manager = BookManager::new(options.clone()).expect("Shipped book");
+            (0..8).map(|_| manager.get_move(&Board::new()).map(|book_move| book_move.mv)).collect::<Vec<_>>()
+        };
+        assert_eq!(picks(), picks());
+
+        let disabled = BookManager::new(BookOptions { enabled: false, ..options.clone() }).expect("Nothing to open");
+        assert!(disabled.get_moves(&Board::new()).is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+use types::{Board, ChessMove};
+use crate::book::{Book, BookMove, MoveStats};
+
+const PAGE_SIZE: usize = 4096;
+const PAGE_HEADER_SIZE: usize = 4;
+const CTO_HEADER_SIZE: usize = 16;
+const STATS_SIZE: usize = 33;
+
+const RED_RECOMMENDATION: u8 = 0x40;
+const GREEN_RECOMMENDATION: u8 = 0x80;
+
+/// The annotation ChessBase attaches to a book move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtgAnnotation {
+    None,
+    Good,
+    Mistake,
+    Excellent,
+    Blunder,
+    Interesting,
+    Dubious,
+    OnlyMove,
+    Zugzwang,
+    Other(u8),
+}
+
+impl CtgAnnotation {
+    pub fn from_byte(byte: u8) -> CtgAnnotation {
+        match byte {
+            0x00 => CtgAnnotation::None,
+            0x01 => CtgAnnotation::Good,
+            0x02 => CtgAnnotation::Mistake,
+            0x03 => CtgAnnotation::Excellent,
+            0x04 => CtgAnnotation::Blunder,
+            0x05 => CtgAnnotation::Interesting,
+            0x06 => CtgAnnotation::Dubious,
+            0x08 => CtgAnnotation::OnlyMove,
+            0x16 => CtgAnnotation::Zugzwang,
+            other => CtgAnnotation::Other(other),
+        }
+    }
+
+    /// Moves marked `?` or `??` are kept out of play.
+    #[inline(always)]
+    pub fn is_bad(self) -> bool {
+        matches!(self, CtgAnnotation::Mistake | CtgAnnotation::Blunder)
+    }
+}
+
+/// Game statistics stored with a position. Wins and losses count for the side to move there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CtgStats {
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub rated_games: u32,
+    pub rating_sum: u32,
+    pub performance_games: u32,
+    pub performance_sum: u32,
+}
+
+impl CtgStats {
+    fn from_bytes(bytes: &[u8]) -> CtgStats {
+        CtgStats {
+            games: read_u24(bytes, 0),
+            wins: read_u24(bytes, 3),
+            losses: read_u24(bytes, 6),
+            draws: read_u24(bytes, 9),
+            rated_games: read_u24(bytes, 16),
+            rating_sum: read_u32(bytes, 19),
+            performance_games: read_u24(bytes, 23),
+            performance_sum: read_u32(bytes, 26),
+        }
+    }
+
+    pub fn average_rating(&self) -> Option<u32> {
+        (self.rated_games > 0).then(|| self.rating_sum / self.rated_games)
+    }
+
+    pub fn performance(&self) -> Option<u32> {
+        (self.performance_games > 0).then(|| self.performance_sum / self.performance_games)
+    }
+}
+
+/// One stored position: its book moves with their annotations, its statistics and ChessBase's markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CtgEntry {
+    pub moves: Vec<(u8, CtgAnnotation)>,
+    pub stats: CtgStats,
+    pub recommendation: u8,
+    pub comment: u8,
+}
+
+impl CtgEntry {
+    /// Parses the entry at the start of `bytes`, returning its encoded position and the bytes it took up.
+    fn parse(bytes: &[u8]) -> Option<(Vec<u8>, CtgEntry, usize)> {
+        let position_length = (*bytes.first()? & 0x1f) as usize;
+        let moves_length = *bytes.get(position_length)? as usize;
+        let stats_start = position_length + moves_length;
+        let stats = bytes.get(stats_start..stats_start + STATS_SIZE)?;
+
+        // The move block starts with its own length, then holds (move, annotation) pairs
+        let moves = bytes
+            .get(position_length + 1..stats_start)?
+            .chunks_exact(2)
+            .map(|pair| (pair[0], CtgAnnotation::from_byte(pair[1])))
+            .collect();
+
+        let entry = CtgEntry {
+            moves,
+            stats: CtgStats::from_bytes(stats),
+            recommendation: stats[30],
+            comment: stats[32],
+        };
+        Some((bytes[..position_length].to_vec(), entry, stats_start + STATS_SIZE))
+    }
+
+    #[inline(always)]
+    pub fn is_recommended(&self) -> bool {
+        self.recommendation & GREEN_RECOMMENDATION != 0
+    }
+
+    #[inline(always)]
+    pub fn is_avoided(&self) -> bool {
+        self.recommendation & RED_RECOMMENDATION != 0
+    }
+}
+
+/// A ChessBase opening book, made of a `.ctg` file of 4 KiB position pages, a `.cto` page index and
+/// a `.ctb` header with the index bounds.
+///
+/// ChessBase finds pages through a hash of the encoded position. Every page the index references is
+/// read on load instead, and positions are kept in a map keyed by their encoding.
+#[derive(Debug, Clone)]
+pub struct CtgBook {
+    bounds: (u32, u32),
+    positions: HashMap<Vec<u8>, CtgEntry>,
+}
+
+impl CtgBook {
+    /// Opens the book from any one of its three files; the other two are found next to it.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let ctb = fs::read(path.with_extension("ctb"))?;
+        let cto = fs::read(path.with_extension("cto"))?;
+        let ctg = fs::read(path.with_extension("ctg"))?;
+        Self::from_parts(&ctb, &cto, &ctg)
+    }
+
+    pub fn from_parts(ctb: &[u8], cto: &[u8], ctg: &[u8]) -> io::Result<Self> {
+        let bounds = Self::read_bounds(ctb)?;
+        let mut positions = HashMap::new();
+
+        for page in Self::read_page_index(cto)? {
+            let start = (page + 1) * PAGE_SIZE; // The first page of the file is a header
+            let bytes = ctg.get(start..start + PAGE_SIZE).ok_or_else(|| invalid_data("CTG page index points past the end of the book"))?;
+            Self::read_page(bytes, &mut positions)?;
+        }
+
+        Ok(CtgBook { bounds, positions })
+    }
+
+    /// Lower and upper bound of the page index slots ChessBase probes.
+    #[inline(always)]
+    pub fn bounds(&self) -> (u32, u32) {
+        self.bounds
+    }
+
+    /// The stored entry for the position, if the book has it.
+    pub fn entry(&self, board: &Board) -> Option<&CtgEntry> {
+        self.positions.get(&board.ctg_position())
+    }
+
+    fn read_bounds(ctb: &[u8]) -> io::Result<(u32, u32)> {
+        if ctb.len() < 12 {
+            return Err(invalid_data("CTB header is too short"));
+        }
+        Ok((read_u32(ctb, 4), read_u32(ctb, 8)))
+    }
+
+    /// The distinct pages the `.cto` index references, in file order; unused slots hold -1.
+    fn read_page_index(cto: &[u8]) -> io::Result<BTreeSet<usize>> {
+        let slots = cto.get(CTO_HEADER_SIZE..).ok_or_else(|| invalid_data("CTO index is too short"))?;
+        Ok(slots
+            .chunks_exact(4)
+            .filter_map(|slot| usize::try_from(i32::from_be_bytes(slot.try_into().expect("4 bytes"))).ok())
+            .collect())
+    }
+
+    fn read_page(page: &[u8], positions: &mut HashMap<Vec<u8>, CtgEntry>) -> io::Result<()> {
+        let count = u16::from_be_bytes([page[0], page[1]]) as usize;
+        let used = (u16::from_be_bytes([page[2], page[3]]) as usize).min(page.len());
+        if used < PAGE_HEADER_SIZE {
+            return Err(invalid_data("CTG page is shorter than its header"));
+        }
+
+        let mut offset = PAGE_HEADER_SIZE;
+        for _ in 0..count {
+            let entry = page.get(offset..used).and_then(CtgEntry::parse);
+            let (position, entry, length) = entry.ok_or_else(|| invalid_data("Truncated CTG entry"))?;
+            positions.insert(position, entry);
+            offset += length;
+        }
+        Ok(())
+    }
+
+    /// The move's statistics from its mover's side, read from the position it leads to.
+    fn reply_entry(&self, board: &Board, mv: ChessMove) -> Option<&CtgEntry> {
+        let mut child = *board;
+        child.make_move(mv);
+        self.entry(&child)
+    }
+}
+
+impl Book for CtgBook {
+    fn moves(&self, board: &Board) -> Vec<BookMove> {
+        let Some(entry) = self.entry(board) else {
+            return Vec::new();
+        };
+
+        let candidates: Vec<(ChessMove, Option<MoveStats>)> = entry
+            .moves
+            .iter()
+            .filter(|(_, annotation)| !annotation.is_bad())
+            .filter_map(|&(code, _)| board.move_from_ctg(code))
+            .filter(|&mv| board.is_legal_move(mv, board.side_to_move))
+            .filter_map(|mv| match self.reply_entry(board, mv) {
+                Some(reply) if reply.is_avoided() => None,
+                // The reply position is stored from the opponent's side, so its wins are the mover's losses
+                Some(reply) => Some((mv, Some(MoveStats {
+                    games: reply.stats.games,
+                    wins: reply.stats.losses,
+                    losses: reply.stats.wins,
+                    priority: reply.is_recommended() as u8,
+                }))),
+                None => Some((mv, None)),
+            })
+            .collect();
+
+        // Weight by points scored, two per win and one per draw, scaled against the best move
+        let points = |stats: &Option<MoveStats>| stats.map_or(0, |stats| stats.wins as u64 * 2 + stats.draws() as u64) + 1;
+        let max_points = candidates.iter().map(|(_, stats)| points(stats)).max().unwrap_or(1);
+
+        let mut moves: Vec<BookMove> = candidates
+            .into_iter()
+            .map(|(mv, stats)| {
+                let weight = (points(&stats) * u16::MAX as u64 / max_points) as u16;
+                BookMove { mv, weight, learn: 0, stats }
+            })
+            .collect();
+
+        moves.sort_by_key(|book_move| Reverse(book_move.weight));
+        moves
+    }
+
+    fn len(&self) -> usize {
+        self.positions.len()
+    }
+}
+
+#[inline(always)]
+fn read_u24(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([0, bytes[offset], bytes[offset + 1], bytes[offset + 2]])
+}
+
+#[inline(always)]
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_bytes(board: &Board, moves: &[(u8, u8)], results: [u32; 3], recommendation: u8) -> Vec<u8> {
+        let mut bytes = board.ctg_position();
+        bytes.push(moves.len() as u8 * 2 + 1);
+        bytes.extend(moves.iter().flat_map(|&(code, annotation)| [code, annotation]));
+
+        let [wins, losses, draws] = results;
+        for count in [wins + losses + draws, wins, losses, draws] {
+            bytes.extend(&count.to_be_bytes()[1..]);
+        }
+        bytes.extend([0; 18]);
+        bytes.extend([recommendation, 0, 0]);
+        bytes
+    }
+
+    /// Builds a one-page book from the page's entry count, its used length and its entries.
+    fn page_book(count: u16, used: u16, body: &[u8]) -> io::Result<CtgBook> {
+        let mut page = vec![0; PAGE_SIZE];
+        page[0..2].copy_from_slice(&count.to_be_bytes());
+        page[2..4].copy_from_slice(&used.to_be_bytes());
+        page[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + body.len()].copy_from_slice(body);
+
+        let ctg = [vec![0; PAGE_SIZE], page].concat();
+        let cto = [vec![0; CTO_HEADER_SIZE], (-1i32).to_be_bytes().to_vec(), 0i32.to_be_bytes().to_vec()].concat();
+        let ctb = [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2];
+        CtgBook::from_parts(&ctb, &cto, &ctg)
+    }
+
+    /// Builds a one-page book holding the given entries.
+    fn book(entries: &[Vec<u8>]) -> CtgBook {
+        let body: Vec<u8> = entries.concat();
+        page_book(entries.len() as u16, (PAGE_HEADER_SIZE + body.len()) as u16, &body).expect("Well-formed book")
+    }
+
+    #[test]
+    fn test_malformed_pages_are_rejected() {
+        let entry = entry_bytes(&Board::new(), &[(0x5f, 0x00)], [1, 0, 0], 0);
+        let used = (PAGE_HEADER_SIZE + entry.len()) as u16;
+        assert!(page_book(1, used, &entry).is_ok());
+
+        // A move block length of 0 would end the block before it starts
+        let mut empty_moves = entry.clone();
+        empty_moves[(entry[0] & 0x1f) as usize] = 0;
+        assert_eq!(page_book(1, used, &empty_moves).map_err(|error| error.kind()).err(), Some(io::ErrorKind::InvalidData));
+
+        // A used length shorter than the header, and more entries than the used length holds
+        assert_eq!(page_book(1, 2, &entry).map_err(|error| error.kind()).err(), Some(io::ErrorKind::InvalidData));
+        assert_eq!(page_book(2, used, &entry).map_err(|error| error.kind()).err(), Some(io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_moves_use_annotations_and_reply_stats() {
+        let start = Board::new();
+        let after = |uci: &str| {
+            let mut board = start;
+            board.make_move(board.parse_uci_move(uci).expect("Valid UCI move"));
+            board
+        };
+
+        // 1.e4!, 1.Nf3 and 1.d4?; replies are stored from Black's side, so their losses are White's wins
+        let book = book(&[
+            entry_bytes(&start, &[(0x5f, 0x01), (0xf3, 0x00), (0xcf, 0x02)], [40, 40, 20], 0),
+            entry_bytes(&after("e2e4"), &[], [10, 30, 10], 0),
+            entry_bytes(&after("g1f3"), &[], [20, 20, 10], 0),
+        ]);
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.bounds(), (1, 2));
+        assert_eq!(book.entry(&start).map(|entry| entry.moves[0].1), Some(CtgAnnotation::Good));
+
+        let moves = book.moves(&start);
+        let names: Vec<String> = moves.iter().map(|book_move| book_move.mv.to_string()).collect();
+        assert_eq!(names, vec!["e2e4", "g1f3"]);
+
+        let stats = moves[0].stats.expect("Reply is in the book");
+        assert_eq!((stats.games, stats.wins, stats.losses, stats.draws()), (50, 30, 10, 10));
+        assert_eq!(moves[0].weight, u16::MAX);
+    }
+
+    #[test]
+    fn test_red_replies_are_skipped() {
+        let start = Board::new();
+        let mut after = start;
+        after.make_move(start.parse_uci_move("e2e4").expect("Valid UCI move"));
+
+        let book = book(&[
+            entry_bytes(&start, &[(0x5f, 0x00), (0xf3, 0x00)], [1, 1, 1], 0),
+            entry_bytes(&after, &[], [1, 1, 1], RED_RECOMMENDATION),
+        ]);
+        let moves = book.moves(&start);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].mv.to_string(), "g1f3");
+        assert_eq!(moves[0].stats, None);
+    }
+
+    #[test]
+    fn test_shipped_index_without_positions_file() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/books/CTG/Perfect2023");
+        let pages = CtgBook::read_page_index(&fs::read(base.with_extension("cto")).expect("Shipped CTO index")).expect("Valid index");
+        assert_eq!(pages.len(), 1024);
+        assert_eq!(CtgBook::read_bounds(&fs::read(base.with_extension("ctb")).expect("Shipped CTB header")).ok(), Some((0x380, 0x510)));
+
+        // Only the index and header are shipped, so opening the book reports the missing positions file
+        assert_eq!(CtgBook::load(base.with_extension("cto")).err().map(|error| error.kind()), Some(io::ErrorKind::NotFound));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::transposition::TranspositionTable;
-use types::{Board, Color, Square, Zobrist};
+use types::{Board, ChessMove, Color};
 use crate::Search;
 
 impl Search{
@@ -7,16 +7,17 @@ impl Search{
         board: &mut Board,
         max_depth: i32,
         color: Color,
+        history: &mut Vec<u64>,
         transposition_table: &mut TranspositionTable,
-    ) -> (Square, Square) {
+    ) -> ChessMove {
        let mut best_move = None;
     let mut best_eval = i32::MIN;
     let mut alpha = i32::MIN;
     let beta = i32::MAX;
-    let zobrist_key = board.compute_zobrist_hash(&Zobrist::new());
+    let zobrist_key = board.hash();
 
     for depth in 1..=max_depth {
-        let eval = Self::alpha_beta(board, depth, alpha, beta, color, transposition_table).0;
+        let eval = Self::alpha_beta(board, depth, alpha, beta, color, history, transposition_table).0;
 
         // Assuming we're maximizing for the current color
         if eval > best_eval {
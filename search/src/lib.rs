@@ -7,8 +7,22 @@ pub mod null_move;
 pub mod lmr;
 pub mod search;
 pub mod opening_book;
-mod polyglot_book;
+pub mod book;
+pub mod abk_book;
+pub mod ctg_book;
+pub mod polyglot_book;
+pub mod book_builder;
+pub mod book_learning;
+pub mod book_export;
+pub mod book_manager;
 
 pub use search::Search;
 
-pub use opening_book::OpeningBook;
\ No newline at end of file
+pub use opening_book::{OpeningBook, SelectionPolicy};
+pub use book::{load_book, probe, walk_book, Book, BookMove, MoveStats, ProbedMove};
+pub use polyglot_book::{PolyglotBook, PolyglotEntry};
+pub use abk_book::{AbkBook, AbkEntry, AbkSettings};
+pub use ctg_book::{CtgAnnotation, CtgBook, CtgEntry, CtgStats};
+pub use book_builder::{BookBuildOptions, BookBuilder};
+pub use book_learning::{BookLearning, Outcome};
+pub use book_manager::{BookManager, BookOptions};
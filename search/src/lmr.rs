@@ -9,22 +9,28 @@ impl Search{
     alpha: i32,
     beta: i32,
     color: Color,
+    history: &mut Vec<u64>,
     transposition_table: &mut TranspositionTable,
 ) -> i32 {
     if depth <= 1 {
-        return Self::alpha_beta(board, depth, alpha, beta, color, transposition_table).0;
+        return Self::alpha_beta(board, depth, alpha, beta, color, history, transposition_table).0;
     }
 
     let moves = board.generate_legal_moves(color);
     let mut best_eval = i32::MIN;
     let mut alpha = alpha;
 
-    for (index, (from, to)) in moves.iter().enumerate() {
+    for (index, mv) in moves.iter().enumerate() {
         let reduction = if index > 3 { 1 } else { 0 };
-        let (piece, color) = board.get_piece_at_square(*from).expect("Could not get piece at a given square");
-        board.make_move(*from, *to, piece, color);
-        let eval = -Self::alpha_beta(board, depth - 1 - reduction, -beta, -alpha, color.opponent(), transposition_table).0;
-        board.unmake_move(*from, *to, piece, color);
+        history.push(board.hash());
+        let undo = board.make_move(*mv);
+        let eval = if Self::is_draw(board, history) {
+            0
+        } else {
+            -Self::alpha_beta(board, depth - 1 - reduction, -beta, -alpha, color.opponent(), history, transposition_table).0
+        };
+        board.unmake_move(*mv, undo);
+        history.pop();
 
         best_eval = best_eval.max(eval);
         alpha = alpha.max(eval);
@@ -1,17 +1,20 @@
+use std::cmp::Reverse;
 use eval::Evaluation;
-use crate::transposition::TranspositionTable;
-use types::{Board, Color, GameState, Square};
+use crate::transposition::{NodeType, TranspositionEntry, TranspositionTable};
+use types::{Board, ChessMove, Color, GameState};
 use crate::Search;
 
 impl Search {
     /// This function returns the best move for the given board and color by performing a search.
+    /// `history` holds the hashes of the positions played before this one, oldest first.
     pub fn best_move(
         board: &mut Board,
         max_depth: i32,
         color: Color,
+        history: &mut Vec<u64>,
         transposition_table: &mut TranspositionTable,
-    ) -> (Square, Square) {
-        let (_, best_move) = Self::negamax(board, max_depth, i32::MIN, i32::MAX, color, transposition_table);
+    ) -> ChessMove {
+        let (_, best_move) = Self::negamax(board, max_depth, i32::MIN, i32::MAX, color, history, transposition_table);
         best_move.expect("No valid move found")
     }
 
@@ -21,8 +24,9 @@ impl Search {
     mut alpha: i32,
     beta: i32,
     color: Color,
+    history: &mut Vec<u64>,
     transposition_table: &mut TranspositionTable,
-) -> (i32, Option<(Square, Square)>) {
+) -> (i32, Option<ChessMove>) {
     if depth == 0 {
         return (Evaluation::evaluate(board, color), None);
     }
@@ -30,27 +34,38 @@ impl Search {
     if GameState::is_checkmate(board, color) {
         return (-9999, None); // Negative high value for checkmate
     } else if GameState::is_stalemate(board, color) {
-        return (0, None); // Stalemate results in a draw
+        // Stalemate results in a draw, except in Shatranj where it loses like checkmate
+        return (if board.variant().stalemate_loses() { -9999 } else { 0 }, None);
+    } else if let Some(winner) = GameState::bare_king_winner(board) {
+        return (if winner == color { 9999 } else { -9999 }, None);
     }
 
+    let original_alpha = alpha;
     let mut best_score = i32::MIN + 1;  // Initialize with a value just above the minimum to prevent issues
     let mut best_move = None;
 
-    let legal_moves = board.generate_legal_moves(color);
+    let mut legal_moves = board.generate_legal_moves(color);
     if legal_moves.is_empty() {
         return (0, None); // No legal moves, return a draw score
     }
 
+    // Winning captures first and losing ones last, so cutoffs come early
+    legal_moves.sort_by_cached_key(|&mv| Reverse(if mv.is_capture() { board.see(mv) } else { 0 }));
+
     // Handle potential overflow for alpha and beta when negating
     let mut neg_alpha = if alpha == i32::MIN { i32::MAX } else { -alpha };
     let neg_beta = if beta == i32::MIN { i32::MAX } else { -beta };
 
     for m in legal_moves {
-        let piece = board.get_piece_at_square(m.0).expect("No piece found at source square").0;
-
-        board.make_move(m.0, m.1, piece, color);
-        let mut score = Self::negamax(board, depth - 1, neg_beta, neg_alpha, color.opponent(), transposition_table).0;
-        board.unmake_move(m.0, m.1, piece, color);
+        history.push(board.hash());
+        let undo = board.make_move(m);
+        let mut score = if Self::is_draw(board, history) {
+            0
+        } else {
+            Self::negamax(board, depth - 1, neg_beta, neg_alpha, color.opponent(), history, transposition_table).0
+        };
+        board.unmake_move(m, undo);
+        history.pop();
 
         // Handle the potential overflow condition for the score
         if score == i32::MIN {
@@ -61,7 +76,7 @@ impl Search {
 
         if score > best_score {
             best_score = score;
-            best_move = Some((m.0, m.1));
+            best_move = Some(m);
         }
 
         alpha = alpha.max(score);
@@ -73,8 +88,49 @@ impl Search {
         neg_alpha = if alpha == i32::MIN { i32::MAX } else { -alpha };
     }
 
+    // Remember the result so iterative deepening can pick the best move back up
+    let node_type = if best_score <= original_alpha {
+        NodeType::Alpha
+    } else if best_score >= beta {
+        NodeType::Beta
+    } else {
+        NodeType::Exact
+    };
+    transposition_table.store(board.hash(), TranspositionEntry { depth, score: best_score, node_type, best_move });
+
     (best_score, best_move)
 }
 
+    /// Whether a position reached in the search is drawn. A single earlier occurrence already counts as a
+    /// repetition here: if repeating is best for both sides, the game will repeat until it is drawn.
+    pub fn is_draw(board: &Board, history: &[u64]) -> bool {
+        GameState::repetitions(board, history) >= 2
+            || GameState::has_insufficient_material(board)
+            || (board.halfmove_clock() >= 100 && !GameState::is_checkmate(board, board.side_to_move))
+    }
+
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_bounds() {
+        let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").expect("Valid FEN");
+        let mut table = TranspositionTable::new();
+
+        // With the full window the root score is exact
+        let (score, best_move) = Search::negamax(&mut board, 2, i32::MIN, i32::MAX, Color::White, &mut Vec::new(), &mut table);
+        assert_eq!(best_move.map(|mv| mv.to_string()), Some("a1a8".to_string()));
+        let entry = table.get(board.hash()).expect("Root entry");
+        assert_eq!((entry.score, entry.node_type), (score, NodeType::Exact));
+
+        // A window above the mate score can only bound it from above, one below it only from below
+        Search::negamax(&mut board, 2, 10_000, 10_001, Color::White, &mut Vec::new(), &mut table);
+        assert_eq!(table.get(board.hash()).expect("Root entry").node_type, NodeType::Alpha);
+        Search::negamax(&mut board, 2, -10_001, -10_000, Color::White, &mut Vec::new(), &mut table);
+        assert_eq!(table.get(board.hash()).expect("Root entry").node_type, NodeType::Beta);
+    }
+}
@@ -9,21 +9,24 @@ impl Search{
     alpha: i32,
     beta: i32,
     color: Color,
+    history: &mut Vec<u64>,
     transposition_table: &mut TranspositionTable,
 ) -> i32 {
     if depth <= 1 || board.is_endgame() {
-        return Self::alpha_beta(board, depth, alpha, beta, color, transposition_table).0;
+        return Self::alpha_beta(board, depth, alpha, beta, color, history, transposition_table).0;
     }
 
     // Perform a null move (skip opponent's turn)
-    board.do_null_move();
-    let score = -Self::alpha_beta(board, depth - 1 - 2, -beta, -alpha, color.opponent(), transposition_table).0;
-    board.undo_null_move();
+    history.push(board.hash());
+    let undo = board.do_null_move();
+    let score = -Self::alpha_beta(board, depth - 1 - 2, -beta, -alpha, color.opponent(), history, transposition_table).0;
+    board.undo_null_move(undo);
+    history.pop();
 
     if score >= beta {
         return beta;
     }
 
-    Self::alpha_beta(board, depth, alpha, beta, color, transposition_table).0
+    Self::alpha_beta(board, depth, alpha, beta, color, history, transposition_table).0
 }
 }
@@ -1,66 +1,174 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufReader, Read};
-use types::Square;
-use std::env;
-use std::path::{Path, PathBuf};
+use std::cmp::Reverse;
+use std::fmt;
+use std::io;
+use rand::Rng;
+use std::str::FromStr;
+use types::{Board, ChessMove};
+use std::path::Path;
+use crate::book::{load_book, Book, BookMove};
+use crate::book_learning::{BookLearning, Outcome};
 
+/// How a move is picked when the book offers several for a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionPolicy {
+    /// Always the highest-weighted move.
+    #[default]
+    BestWeight,
+    /// Random, with each move's chance proportional to its weight.
+    WeightedRandom,
+    /// Random, every book move equally likely.
+    Uniform,
+}
+
+impl SelectionPolicy {
+    /// Picks one of `moves`, which must be sorted by descending weight as `Book::moves` returns them.
+    pub fn choose<R: Rng>(self, moves: &[BookMove], rng: &mut R) -> Option<BookMove> {
+        if moves.is_empty() {
+            return None;
+        }
+
+        match self {
+            SelectionPolicy::BestWeight => moves.first().copied(),
+            SelectionPolicy::Uniform => moves.get(rng.gen_range(0..moves.len())).copied(),
+            SelectionPolicy::WeightedRandom => {
+                let total: u32 = moves.iter().map(|book_move| book_move.weight as u32).sum();
+                if total == 0 {
+                    return moves.get(rng.gen_range(0..moves.len())).copied();
+                }
+
+                // Walk the cumulative weights until the random pick falls inside a move's share
+                let mut pick = rng.gen_range(0..total);
+                moves.iter().copied().find(|book_move| {
+                    let weight = book_move.weight as u32;
+                    if pick < weight {
+                        true
+                    } else {
+                        pick -= weight;
+                        false
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for SelectionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionPolicy::BestWeight => write!(f, "best"),
+            SelectionPolicy::WeightedRandom => write!(f, "weighted"),
+            SelectionPolicy::Uniform => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for SelectionPolicy {
+    type Err = String;
+
+    /// Accepts the names used on the command line and in engine options: `best`, `weighted` and `random`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "best" => Ok(SelectionPolicy::BestWeight),
+            "weighted" => Ok(SelectionPolicy::WeightedRandom),
+            "random" | "uniform" => Ok(SelectionPolicy::Uniform),
+            other => Err(format!("Unknown book variety '{}' (expected best, weighted or random)", other)),
+        }
+    }
+}
+
+/// A book file of any supported format, with its learning sidecar applied to the weights.
+/// Choosing between the moves is left to `BookManager`.
 #[derive(Debug)]
 pub struct OpeningBook {
     file_path: String, // Path to the opening book file
-    book: HashMap<u64, Vec<(u16, String)>>, // Hash of position -> (move, opening name)
+    book: Box<dyn Book>,
+    learning: BookLearning,
+    played: Vec<(u64, u16)>, // Book moves picked this game, as (Polyglot key, Polyglot move)
 }
 
 impl OpeningBook {
-    // Constructor to create an OpeningBook with a file path
-    pub fn new(file_path: &str) -> Self {
-        let exe_path = env::current_exe().expect("Failed to get current executable path");
-        let exe_dir = exe_path.parent().expect("Failed to get the executable directory").parent().unwrap().parent().unwrap();
-         
-        let relative_path = Path::new(file_path);
-        let book_file: PathBuf = exe_dir.join(relative_path);
-        OpeningBook {
-            file_path: book_file.to_str().unwrap().to_string(),
-            book: HashMap::new(),
+    /// Opens a book at the given path as-is, together with its learning sidecar.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        Ok(OpeningBook {
+            file_path: path.display().to_string(),
+            book: load_book(path)?,
+            learning: BookLearning::load(BookLearning::sidecar_path(path))?,
+            played: Vec::new(),
+        })
+    }
+
+    #[inline(always)]
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
+
+    /// Every legal book move for the position, most heavily weighted first once learned results are applied.
+    pub fn get_moves(&self, board: &Board) -> Vec<BookMove> {
+        let key = board.polyglot_key();
+        let mut moves = self.book.moves(board);
+        for book_move in &mut moves {
+            self.learning.adjust(key, book_move);
         }
+
+        moves.sort_by_key(|book_move| Reverse(book_move.weight));
+        moves
+    }
+
+    /// Notes a move played from this book, for when the game's outcome is passed to `learn`.
+    pub fn remember(&mut self, board: &Board, mv: ChessMove) {
+        self.played.push((board.polyglot_key(), mv.to_polyglot()));
     }
 
-    // Method to load a Polyglot book from the specified file
-    pub fn load_from_file(&mut self) -> Result<(), io::Error> {
-        println!("Opening book file: {}", self.file_path);
-        let file = File::open(&self.file_path)?;
-        let mut reader = BufReader::new(file);
-
-        let mut buffer = [0; 16]; // Each entry in Polyglot book is 16 bytes
-        while reader.read_exact(&mut buffer).is_ok() {
-            let position_hash = u64::from_be_bytes(buffer[0..8].try_into().unwrap());
-            let polyglot_move = u16::from_be_bytes(buffer[8..10].try_into().unwrap());
-            let opening_name = String::from_utf8_lossy(&buffer[10..]).to_string();
-
-            // Insert the move into the book with its associated opening name
-            self.book.entry(position_hash)
-                .or_default()
-                .push((polyglot_move, opening_name));
+    /// Credits the finished game's outcome to every book move picked since the last call, and saves the results.
+    pub fn learn(&mut self, outcome: Outcome) -> io::Result<()> {
+        for (key, raw_move) in self.played.drain(..) {
+            self.learning.update(key, raw_move, outcome);
         }
+        self.learning.save()
+    }
+}
 
-        println!("Opening book loaded successfully");
-        Ok(())
+impl Book for OpeningBook {
+    fn moves(&self, board: &Board) -> Vec<BookMove> {
+        self.get_moves(board)
     }
 
-    // Method to retrieve a move from the book based on the current board position
-    pub fn get_move(&self, position_hash: u64) -> Option<(u16, String)> {
-        self.book.get(&position_hash).and_then(|moves| moves.first().cloned())
+    fn len(&self) -> usize {
+        self.book.len()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use types::{MoveFlag, Square};
 
-    // Method to get the opening name based on the current board position
-    pub fn get_opening_name(&self, position_hash: u64) -> Option<String> {
-        self.book.get(&position_hash).and_then(|moves| moves.first().map(|(_, name)| name.clone()))
+    fn book_move(from: Square, to: Square, weight: u16) -> BookMove {
+        BookMove { mv: ChessMove::new(from, to, MoveFlag::Quiet), weight, learn: 0, stats: None }
     }
-    
-    // Convert a u16 Polyglot move to (Square, Square) format
-    pub fn polyglot_move_to_squares(polyglot_move: u16) -> (Square, Square) {
-        let from_square = Square::from_index(((polyglot_move >> 6) & 0x3F) as usize); // Extract the source square
-        let to_square = Square::from_index((polyglot_move & 0x3F) as usize); // Extract the destination square
-        (from_square, to_square)
+
+    #[test]
+    fn test_selection_policies() {
+        let moves = [book_move(Square::E2, Square::E4, 30), book_move(Square::D2, Square::D4, 10), book_move(Square::C2, Square::C4, 0)];
+        let mut rng = StdRng::seed_from_u64(42);
+
+        assert_eq!(SelectionPolicy::BestWeight.choose(&moves, &mut rng), Some(moves[0]));
+        assert_eq!(SelectionPolicy::Uniform.choose(&[], &mut rng), None);
+
+        // A zero-weight move is never picked at random, and the heavier move wins roughly 3 in 4
+        let picks: Vec<BookMove> = (0..400).filter_map(|_| SelectionPolicy::WeightedRandom.choose(&moves, &mut rng)).collect();
+        assert!(picks.iter().all(|pick| pick.weight > 0));
+        let heavy = picks.iter().filter(|pick| pick.weight == 30).count();
+        assert!((250..350).contains(&heavy), "picked the heavy move {} times", heavy);
+
+        // The same seed replays the same choices
+        let replay = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10).map(|_| SelectionPolicy::Uniform.choose(&moves, &mut rng)).collect::<Vec<_>>()
+        };
+        assert_eq!(replay(7), replay(7));
     }
 }
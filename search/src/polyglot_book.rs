@@ -1,73 +1,164 @@
-use std::fs::File;
-use std::io::{self, Read, BufReader};
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
 use std::path::Path;
-use types::Square;
-
-#[derive(Debug)]
-struct PolyglotEntry {
-    key: u64,
-    move_: u16,
-    weight: u16,
-    learn: u32,
-    next: u32,
+use types::{Board, ChessMove};
+use crate::book::{Book, BookMove};
+
+const ENTRY_SIZE: usize = 16;
+
+/// One 16-byte record of a Polyglot `.bin` book; all fields are stored big-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolyglotEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
 }
 
 impl PolyglotEntry {
-    fn from_bytes(bytes: &[u8]) -> PolyglotEntry {
-        let key = u64::from_be_bytes(bytes[0..8].try_into().unwrap());
-        let move_ = u16::from_be_bytes(bytes[8..10].try_into().unwrap());
-        let weight = u16::from_be_bytes(bytes[10..12].try_into().unwrap());
-        let learn = u32::from_be_bytes(bytes[12..16].try_into().unwrap());
-
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> PolyglotEntry {
         PolyglotEntry {
-            key,
-            move_,
-            weight,
-            learn,
-            next: 0, // Polyglot doesn't use this field, but we keep it for completeness.
+            key: u64::from_be_bytes(bytes[0..8].try_into().expect("8 key bytes")),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().expect("2 move bytes")),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().expect("2 weight bytes")),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().expect("4 learn bytes")),
         }
     }
 
-    fn polyglot_move_to_squares(mv: u16) -> (Square, Square) {
-        let from_square = Square::from_index(((mv >> 6) & 0x3f) as usize);
-        let to_square = Square::from_index((mv & 0x3f) as usize);
-        (from_square, to_square)
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+
+    /// Decodes the move against the position it belongs to, turning king-takes-rook into a castle.
+    pub fn to_move(&self, board: &Board) -> ChessMove {
+        board.move_from_polyglot(self.raw_move)
     }
 }
 
-fn read_polyglot_book<P: AsRef<Path>>(path: P) -> io::Result<Vec<PolyglotEntry>> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
-    let mut entries = Vec::new();
-    let mut buffer = [0; 16];
+/// A Polyglot opening book held in memory, sorted by key so positions are found by binary search.
+#[derive(Debug, Clone, Default)]
+pub struct PolyglotBook {
+    entries: Vec<PolyglotEntry>,
+}
+
+impl PolyglotBook {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Polyglot book size is not a multiple of 16 bytes"));
+        }
+
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| PolyglotEntry::from_bytes(chunk.try_into().expect("Chunks are exactly 16 bytes")))
+            .collect();
 
-    while reader.read_exact(&mut buffer).is_ok() {
-        let entry = PolyglotEntry::from_bytes(&buffer);
-        entries.push(entry);
+        Ok(Self::from_entries(entries))
     }
 
-    Ok(entries)
+    /// Builds a book from entries in any order; they are sorted here the way the file format requires.
+    pub fn from_entries(mut entries: Vec<PolyglotEntry>) -> Self {
+        // Well-formed books are already sorted, so this is usually a single pass
+        if !entries.is_sorted_by_key(|entry| entry.key) {
+            entries.sort_by_key(|entry| entry.key);
+        }
+        PolyglotBook { entries }
+    }
+
+    #[inline(always)]
+    pub fn entries(&self) -> &[PolyglotEntry] {
+        &self.entries
+    }
+
+    /// All entries stored for the given Polyglot key, in file order.
+    pub fn entries_for(&self, key: u64) -> &[PolyglotEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = start + self.entries[start..].partition_point(|entry| entry.key == key);
+        &self.entries[start..end]
+    }
+
+    /// Writes the book back out in the sorted 16-byte Polyglot layout.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let bytes: Vec<u8> = self.entries.iter().flat_map(|entry| entry.to_bytes()).collect();
+        fs::write(path, bytes)
+    }
 }
 
-fn find_polyglot_move(entries: &[PolyglotEntry], zobrist_hash: u64) -> Option<PolyglotEntry> {
-    let mut best_entry: Option<PolyglotEntry> = None;
-
-    for entry in entries {
-        if entry.key == zobrist_hash {
-            match &best_entry {
-                Some(current_best) => {
-                    // Replace the current best entry if the new one has a higher weight
-                    if entry.weight > current_best.weight {
-                        best_entry = Some(entry.clone());
-                    }
-                }
-                None => {
-                    // First matching entry found
-                    best_entry = Some(*entry.clone());
-                }
-            }
-        }
+impl Book for PolyglotBook {
+    fn moves(&self, board: &Board) -> Vec<BookMove> {
+        let mut moves: Vec<BookMove> = self
+            .entries_for(board.polyglot_key())
+            .iter()
+            .map(|entry| BookMove { mv: entry.to_move(board), weight: entry.weight, learn: entry.learn, stats: None })
+            .filter(|book_move| board.is_legal_move(book_move.mv, board.side_to_move))
+            .collect();
+
+        moves.sort_by_key(|book_move| Reverse(book_move.weight));
+        moves
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Square;
+
+    fn entry(key: u64, from: Square, to: Square, weight: u16) -> PolyglotEntry {
+        let raw_move = (to.to_index() | from.to_index() << 6) as u16;
+        PolyglotEntry { key, raw_move, weight, learn: 0 }
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let original = PolyglotEntry { key: 0x463b96181691fc9c, raw_move: 0x031c, weight: 65520, learn: 7 };
+        assert_eq!(PolyglotEntry::from_bytes(&original.to_bytes()), original);
     }
 
-    best_entry
+    #[test]
+    fn test_moves_are_found_by_key_and_sorted_by_weight() {
+        let board = Board::new();
+        let key = board.polyglot_key();
+        let book = PolyglotBook::from_entries(vec![
+            entry(key + 1, Square::A2, Square::A3, 9),
+            entry(key, Square::D2, Square::D4, 10),
+            entry(key - 1, Square::A2, Square::A3, 9),
+            entry(key, Square::E2, Square::E4, 20),
+            entry(key, Square::E2, Square::E5, 30), // Not legal, dropped
+        ]);
+
+        assert_eq!(book.entries_for(key).len(), 3);
+        let moves: Vec<String> = book.moves(&board).iter().map(|book_move| book_move.mv.to_string()).collect();
+        assert_eq!(moves, vec!["e2e4", "d2d4"]);
+    }
+
+    #[test]
+    fn test_castling_is_decoded_from_king_takes_rook() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").expect("Valid FEN");
+        let book = PolyglotBook::from_entries(vec![entry(board.polyglot_key(), Square::E1, Square::H1, 1)]);
+
+        let moves = book.moves(&board);
+        assert_eq!(moves.len(), 1);
+        assert!(moves[0].mv.is_castle());
+        assert_eq!(moves[0].mv.to(), Square::G1);
+    }
+
+    #[test]
+    fn test_shipped_book_has_startpos_moves() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/books/Perfect2023.bin");
+        let book = PolyglotBook::load(path).expect("Shipped Polyglot book");
+
+        let moves = book.moves(&Board::new());
+        assert_eq!(moves.first().map(|book_move| book_move.mv.to_string()), Some("e2e4".to_string()));
+        assert_eq!(moves.len(), 4);
+    }
 }
@@ -5,7 +5,7 @@ use crate::Search;
 impl Search{
     pub fn quiescence_search(board: &mut Board, alpha: i32, beta: i32, color: Color) -> i32 {
 
-        let stand_pat = Evaluation::evaluate(&board, color);
+        let stand_pat = Evaluation::evaluate(board, color);
     if stand_pat >= beta {
         return beta;
     }
@@ -15,11 +15,14 @@ impl Search{
 
     let moves = board.generate_captures(color);
 
-    for (from, to) in moves {
-        let (piece, color) = board.get_piece_at_square(from).expect("Could not get piece at a given square");
-        board.make_move(from, to, piece, color);
+    for (mv, see) in moves {
+        if see < 0 {
+            break; // Captures come best first, so every one from here on loses material
+        }
+
+        let undo = board.make_move(mv);
         let eval = -Self::quiescence_search(board, -beta, -alpha, color.opponent());
-        board.unmake_move(from, to, piece, color);
+        board.unmake_move(mv, undo);
 
         best_eval = best_eval.max(eval);
         alpha = alpha.max(eval);
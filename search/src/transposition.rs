@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use types::Square;
+use types::ChessMove;
 
 pub struct TranspositionTable {
     table: HashMap<u64, TranspositionEntry>,
@@ -9,13 +9,18 @@ pub struct TranspositionTable {
 pub struct TranspositionEntry {
     pub depth: i32,
     pub score: i32,
-    pub best_move: Option<(Square, Square)>,
+    /// How far `score` can be trusted: the search window cut it off unless it is exact.
+    pub node_type: NodeType,
+    pub best_move: Option<ChessMove>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NodeType {
+    /// The score fell inside the window.
     Exact,
+    /// No move raised alpha, so the score is only an upper bound.
     Alpha,
+    /// A move reached beta and cut the search off, so the score is only a lower bound.
     Beta,
 }
 
@@ -39,7 +44,7 @@ impl TranspositionTable {
         self.table.insert(zobrist_key, entry);
     }
 
-    pub fn lookup_best_move(&self, zobrist_key: u64) -> Option<(Square, Square)> {
+    pub fn lookup_best_move(&self, zobrist_key: u64) -> Option<ChessMove> {
         if let Some(entry) = self.table.get(&zobrist_key) {
             entry.best_move
         } else {
@@ -1,12 +1,14 @@
+use std::fs;
 use std::io;
 use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
 use arbiter::Arbiter;
-use types::{Board, GameState, Square, ChessMove, Color, Zobrist};
-use search::OpeningBook;
-use std::str::FromStr;
+use types::{Board, ChessMove, Color, Game, GameMove, GameOutcome, GameState, PgnDatabase, Variant};
+use search::{book_export, load_book, probe, BookBuildOptions, BookBuilder, BookManager, OpeningBook, Outcome};
 
-/// TODO: Add the ability to signal to the engine the color the player wants to play with, either w,b or r for random.
-/// TODO: Add debug statements to highlight the issues to me.
+// TODO: Add the ability to signal to the engine the color the player wants to play with, either w,b or r for random.
+// TODO: Add debug statements to highlight the issues to me.
 
 // #[inline(always)]
 // pub fn run_play(depth: u32, player_color: Color) {
@@ -82,34 +84,30 @@ use std::str::FromStr;
 
 
 #[inline(always)]
-pub fn run_play(depth: u32, player_color: Color, opening_book: &OpeningBook) {
+pub fn run_play(depth: u32, player_color: Color, opening_books: &mut BookManager, variant: Variant) {
     let mut engine = Arbiter::new();
-    let mut board = Board::new();
+    let mut board = Board::new_variant(variant);
     let engine_color = player_color.opponent();
-    let mut in_opening_phase = true;
+    let mut in_opening_phase = variant == Variant::Standard; // The books only know modern chess
+    let mut game = Game::from_position(board);
+    let mut history = Vec::new(); // Hashes of every position before the current one, for repetitions
 
     // If the player is White, ask for their move first
     if player_color == Color::White {
         // User's move
-        get_input(&mut board, player_color);
-
-        // Check for game over
-        if GameState::is_game_over(&board, player_color) {
-            println!("Game over! You win.");
-            return;
-        }
+        history.push(board.hash());
+        game.mainline.moves.push(GameMove::new(get_input(&mut board, player_color)));
     }
 
     // Main game loop
-    loop {
-        let zobrist = Zobrist::default();
-        let position_hash = board.compute_zobrist_hash(&zobrist);
-       
+    let outcome = loop {
         if in_opening_phase {
-            if let Some((book_move, opening_name)) = opening_book.get_move(position_hash) {
-                println!("Opening: {}", opening_name);
-                board.apply_move(OpeningBook::polyglot_move_to_squares(book_move), engine_color);
-                println!("Engine move (from book): {:?} -> {:?}", book_move, engine_color);
+            if let Some(book_move) = opening_books.get_move(&board) {
+                let san = board.move_to_san(book_move.mv);
+                history.push(board.hash());
+                board.apply_move(book_move.mv, engine_color);
+                println!("Engine move (from book, weight {}): {} -> {:?}", book_move.weight, san, engine_color);
+                game.mainline.moves.push(GameMove::new(book_move.mv));
             } else {
                 println!("No opening found in book for the current position.");
                 println!("Opening phase complete");
@@ -119,84 +117,215 @@ pub fn run_play(depth: u32, player_color: Color, opening_book: &OpeningBook) {
 
         if !in_opening_phase {
             // Engine's move
-            let best_move = engine.search_best_move(&mut board, depth as i32, engine_color);
+            let best_move = engine.search_best_move(&mut board, depth as i32, engine_color, &history);
+            let san = board.move_to_san(best_move);
+            history.push(board.hash());
             board.apply_move(best_move, engine_color);
-            println!("Engine move: {:?} -> {:?}", best_move.0, best_move.1);
+            println!("Engine move: {}", san);
+            game.mainline.moves.push(GameMove::new(best_move));
         }
 
-        // Check for game over after the engine's move
-        if GameState::is_game_over(&board, engine_color) {
-            println!("Game over! The engine wins.");
-            break;
+        // Check whether the engine's move ended the game
+        if let Some(outcome) = GameState::outcome(&board, &history) {
+            break outcome;
         }
 
         // User's move
-        get_input(&mut board, player_color);
+        history.push(board.hash());
+        game.mainline.moves.push(GameMove::new(get_input(&mut board, player_color)));
 
-        // Check for game over after the player's move
-        if GameState::is_game_over(&board, player_color) {
-            println!("Game over! You win.");
-            break;
+        // Check whether the player's move ended the game
+        if let Some(outcome) = GameState::outcome(&board, &history) {
+            break outcome;
         }
+    };
+
+    let engine_outcome = engine_outcome(outcome, engine_color);
+    match engine_outcome {
+        Outcome::Win => println!("Game over! The engine wins: {}.", outcome),
+        Outcome::Loss => println!("Game over! You win: {}.", outcome),
+        Outcome::Draw => println!("Game over! It is a {}.", outcome),
+    }
+
+    let (white, black) = if player_color == Color::White { ("Player", "Kishmat") } else { ("Kishmat", "Player") };
+    game.set_header("White", white);
+    game.set_header("Black", black);
+    game.set_header("Result", outcome.result());
+    println!("\n{}", game.to_pgn());
+
+    // Let the book learn from how its lines turned out
+    if let Err(error) = opening_books.learn(engine_outcome) {
+        println!("Could not save book learning: {}", error);
+    }
+}
+
+/// How a finished game went from the engine's side of the board.
+#[inline(always)]
+fn engine_outcome(outcome: GameOutcome, engine_color: Color) -> Outcome {
+    match outcome.winner() {
+        Some(winner) if winner == engine_color => Outcome::Win,
+        Some(_) => Outcome::Loss,
+        None => Outcome::Draw,
     }
 }
 
 
+/// Reads moves until the player enters a legal one, in SAN (`Nf3`) or coordinates (`g1f3`), and plays it.
 #[inline(always)]
-pub fn get_input(board: &mut Board, player_color: Color) {
+pub fn get_input(board: &mut Board, player_color: Color) -> ChessMove {
     loop {
         let mut user_input = String::new();
-        println!("Your move (e.g., e2e4): ");
+        println!("Your move (e.g., e4, Nf3 or e2e4): ");
         io::stdout().flush().expect("Failed to flush stdout");
 
         io::stdin().read_line(&mut user_input).expect("Failed to read line");
 
-        let user_move = parse_move(&user_input.trim());
+        let user_move = match board.parse_san(user_input.trim()) {
+            Ok(mv) => Ok(mv),
+            Err(error) => board.parse_uci_move(&parse_move(user_input.trim())).ok_or(error),
+        };
         match user_move {
-            Some(mv) => {
-                println!("Debug: Attempting move {:?}", mv);
+            Ok(mv) => {
                 if board.is_legal_move(mv, player_color) {
-                    board.apply_move(mv.clone(), player_color);
-                    println!("Debug: Move {:?} applied", mv);
-                    break; // Exit the loop if the move is legal
+                    board.apply_move(mv, player_color);
+                    return mv;
                 } else {
                     println!("Illegal move! Please try again.");
                 }
             }
-            None => {
-                println!("Invalid input! Please try again.");
+            Err(error) => {
+                println!("Invalid input ({})! Please try again.", error);
             }
         }
     }
 }
 
+/// Normalizes user input such as "e2-e4" or "E7E8Q" to UCI form.
 #[inline(always)]
-fn parse_move(input: &str) -> Option<ChessMove> {
-    let normalized_input: String = input
+fn parse_move(input: &str) -> String {
+    input
         .chars()
         .filter(|c| c.is_alphanumeric())
         .collect::<String>()
-        .to_lowercase();
+        .to_lowercase()
+}
+
+
+/// Parses a FEN given on the command line under the given rules, reporting why it was rejected.
+fn load_fen(fen: &str, variant: Variant) -> Option<Board> {
+    match Board::from_fen_variant(fen, variant) {
+        Ok(board) => Some(board),
+        Err(error) => {
+            println!("Invalid FEN '{}': {}", fen, error);
+            None
+        }
+    }
+}
+
+#[inline(always)]
+pub fn run_analyze(fen: &str, depth: u32, variant: Variant) {
+    let mut engine = Arbiter::new();
+    let Some(mut board) = load_fen(fen, variant) else {
+        return;
+    };
+    let color = Color::White;  // Assume analyzing for White
+
+    let best_move = engine.search_best_move(&mut board, depth as i32, color, &[]);
+    println!("Best move for position: {}", best_move);
+}
 
-    if normalized_input.len() != 4 {
-        return None;
+#[inline(always)]
+pub fn run_perft(fen: &str, depth: u32, chess960: bool, variant: Variant) {
+    let Some(mut board) = load_fen(fen, variant) else {
+        return;
+    };
+    if chess960 {
+        board.set_chess960(true);
+    }
+    let start = Instant::now();
+
+    // Per-move breakdown first, so a mismatch against another engine can be narrowed down
+    let divide = board.divide(depth);
+    for (mv, nodes) in &divide {
+        println!("{}: {}", board.move_to_uci(*mv), nodes);
     }
 
-    let (source, destination) = normalized_input.split_at(2);
+    let total: u64 = if depth == 0 { 1 } else { divide.iter().map(|(_, nodes)| nodes).sum() };
+    let elapsed = start.elapsed();
+    println!();
+    println!("Nodes searched: {}", total);
+    println!("Time: {} ms ({:.0} nps)", elapsed.as_millis(), total as f64 / elapsed.as_secs_f64().max(1e-9));
+}
+
+#[inline(always)]
+pub fn run_book_build(pgn_path: &str, out_path: &str, options: BookBuildOptions) {
+    let database = PgnDatabase::open(pgn_path).expect("Could not open PGN file");
 
-    let source_square = Square::from_str(source).ok()?;
-    let destination_square = Square::from_str(destination).ok()?;
+    // Unreadable games are reported and left out rather than ending the build
+    let mut builder = BookBuilder::new(options);
+    builder.add_games(database.inspect(|game| {
+        if let Err(error) = game {
+            println!("Skipping {}", error);
+        }
+    }));
+    let book = builder.build();
+    book.save(out_path).expect("Could not write book file");
 
-    Some((source_square, destination_square))
+    println!("Games used: {} (skipped {})", builder.games_used(), builder.games_skipped());
+    println!("Wrote {} entries to {}", book.entries().len(), out_path);
 }
 
+#[inline(always)]
+pub fn run_book_convert(in_path: &str, out_path: &str, max_ply: u32) {
+    let book = load_book(in_path).expect("Could not load book");
+    let is_polyglot = Path::new(out_path).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("bin"));
+
+    if is_polyglot {
+        let polyglot = book_export::to_polyglot(book.as_ref(), max_ply);
+        polyglot.save(out_path).expect("Could not write book file");
+        println!("Wrote {} Polyglot entries to {}", polyglot.entries().len(), out_path);
+    } else {
+        let lines = book_export::to_lines(book.as_ref(), max_ply);
+        fs::write(out_path, &lines).expect("Could not write line listing");
+        println!("Wrote {} lines to {}", lines.lines().count(), out_path);
+    }
+}
 
 #[inline(always)]
-pub fn run_analyze(fen: &str, depth: u32) {
-    let mut engine = Arbiter::new();
-    let board = Board::from_fen(fen).expect("Invalid FEN string");
-    let color = Color::White;  // Assume analyzing for White
+pub fn run_book_probe(fen: &str, book_paths: &[&str], depth: u32) {
+    let Some(board) = load_fen(fen, Variant::Standard) else {
+        return;
+    };
+
+    for path in book_paths {
+        let book = match OpeningBook::open(path) {
+            Ok(book) => book,
+            Err(error) => {
+                println!("{}: could not be opened ({})", path, error);
+                continue;
+            }
+        };
+
+        let moves = probe(&book, &board);
+        println!("{} ({} moves in book for this position)", path, moves.len());
+        for probed in &moves {
+            let mut row = format!("  {:<8} weight {:>5}  {:>5.1}%  learn {}", probed.san, probed.book_move.weight, probed.percent, probed.book_move.learn);
+            if let Some(stats) = probed.book_move.stats {
+                row.push_str(&format!("  games {} +{} ={} -{} ({:.1}%)", stats.games, stats.wins, stats.draws(), stats.losses, stats.score_percent()));
+            }
+            println!("{}", row);
+        }
 
-    let best_move = engine.search_best_move(&mut board.clone(), depth as i32, color);
-    println!("Best move for position: {:?} -> {:?}", best_move.0, best_move.1);
+        if depth > 1 && !moves.is_empty() {
+            println!();
+            print!("{}", book_export::to_tree(&book, &board, depth));
+        }
+        println!();
+    }
+}
+
+#[inline(always)]
+pub fn run_book_tree(path: &str, max_ply: u32) {
+    let book = load_book(path).expect("Could not load book");
+    print!("{}", book_export::to_tree(book.as_ref(), &Board::new(), max_ply));
 }
@@ -5,6 +5,7 @@ use std::io::{self, Write};
 pub fn run_interactive() {
     let mut engine = Arbiter::new();
     let mut board = Board::new();
+    let mut history = Vec::new();
     // Assume the player is white for simplicity
 
     loop {
@@ -19,14 +20,17 @@ pub fn run_interactive() {
             let to_square = parse_square(&trimmed[2..4]).expect("Invalid to square");
 
             // Apply the player's move
-            let (piece, color) = board.get_piece_at_square(from_square).expect("Could not get piece at a given square");
-            board.make_move(from_square, to_square, piece, color);
+            let (_, color) = board.get_piece_at_square(from_square).expect("Could not get piece at a given square");
+            let player_move = board.move_from_squares(from_square, to_square, None);
+            history.push(board.hash());
+            board.apply_move(player_move, color);
 
             // Engine's response
-            let best_move = engine.search_best_move(&mut board, 5, color.opponent());
-            println!("Engine move: {:?} -> {:?}", best_move.0, best_move.1);
+            let best_move = engine.search_best_move(&mut board, 5, color.opponent(), &history);
+            println!("Engine move: {}", best_move);
 
-            board.make_move(best_move.0, best_move.1, piece, color.opponent());
+            history.push(board.hash());
+            board.apply_move(best_move, color.opponent());
         } else {
             println!("Invalid move format. Please use the format 'e2e4'.");
         }
@@ -3,10 +3,12 @@
 mod commands;
 mod interface;
 
+use std::env;
 use std::io;
-use clap::{Arg, ArgMatches, Command};
+use std::path::PathBuf;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use interface::run_interactive;
-use commands::{run_analyze, run_play};
+use commands::{run_analyze, run_book_build, run_book_convert, run_book_probe, run_book_tree, run_perft, run_play};
 
 // fn main() {
 //     let matches = Command::new("KishMat Chess Engine CLI")
@@ -108,7 +110,10 @@ use commands::{run_analyze, run_play};
 // }
 
 use rand::Rng;
-use search::OpeningBook;
+use search::{BookBuildOptions, BookManager, BookOptions};
+
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const DEFAULT_BOOK: &str = "assets/books/Perfect2023.bin";
 // To handle random color selection
 
 fn main() {
@@ -132,6 +137,50 @@ fn main() {
                         .long("color")
                         .value_name("COLOR")
                         .help("Choose the color you want to play as (w for White, b for Black, r for Random)")
+                )
+                .arg(
+                    Arg::new("book")
+                        .short('b')
+                        .long("book")
+                        .value_name("BOOK")
+                        .action(ArgAction::Append)
+                        .help("An opening book (.bin, .abk or .ctg); repeat to fall back through several in order"),
+                )
+                .arg(
+                    Arg::new("no-book")
+                        .long("no-book")
+                        .action(ArgAction::SetTrue)
+                        .help("Play without opening books"),
+                )
+                .arg(
+                    Arg::new("book-ply")
+                        .long("book-ply")
+                        .value_name("PLY")
+                        .help("Stop using books after this many half-moves (default 40)"),
+                )
+                .arg(
+                    Arg::new("book-min-weight")
+                        .long("book-min-weight")
+                        .value_name("WEIGHT")
+                        .help("Ignore book moves weighted below this (default 1)"),
+                )
+                .arg(
+                    Arg::new("book-variety")
+                        .long("book-variety")
+                        .value_name("VARIETY")
+                        .help("How to pick among book moves: best, weighted or random (default best)"),
+                )
+                .arg(
+                    Arg::new("book-seed")
+                        .long("book-seed")
+                        .value_name("SEED")
+                        .help("Seed for the weighted and random varieties, to replay the same choices"),
+                )
+                .arg(
+                    Arg::new("variant")
+                        .long("variant")
+                        .value_name("VARIANT")
+                        .help("The rules to play by: standard or shatranj (default standard)"),
                 ),
         )
         .subcommand(
@@ -150,22 +199,208 @@ fn main() {
                         .long("depth")
                         .value_name("DEPTH")
                         .help("Sets the search depth"),
+                )
+                .arg(
+                    Arg::new("variant")
+                        .long("variant")
+                        .value_name("VARIANT")
+                        .help("The rules to play by: standard or shatranj (default standard)"),
+                ),
+        )
+        .subcommand(
+            Command::new("perft")
+                .about("Count the legal move tree of a position, with a per-move breakdown")
+                .arg(
+                    Arg::new("fen")
+                        .short('f')
+                        .long("fen")
+                        .value_name("FEN")
+                        .help("Provide the FEN string of the position (defaults to the starting position)"),
+                )
+                .arg(
+                    Arg::new("depth")
+                        .short('d')
+                        .long("depth")
+                        .value_name("DEPTH")
+                        .help("Sets the perft depth"),
+                )
+                .arg(
+                    Arg::new("chess960")
+                        .long("chess960")
+                        .value_name("INDEX")
+                        .conflicts_with("fen")
+                        .help("Start from the Chess960 position with this number (0-959) and print castles as king takes rook"),
+                )
+                .arg(
+                    Arg::new("variant")
+                        .long("variant")
+                        .value_name("VARIANT")
+                        .help("The rules to play by: standard or shatranj (default standard)"),
+                ),
+        )
+        .subcommand(
+            Command::new("book")
+                .about("Work with opening books")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("build")
+                        .about("Build a Polyglot book from a PGN game collection")
+                        .arg(
+                            Arg::new("pgn")
+                                .long("pgn")
+                                .value_name("PGN")
+                                .required(true)
+                                .help("The PGN file to read games from"),
+                        )
+                        .arg(
+                            Arg::new("out")
+                                .short('o')
+                                .long("out")
+                                .value_name("BOOK")
+                                .required(true)
+                                .help("Where to write the Polyglot .bin book"),
+                        )
+                        .arg(
+                            Arg::new("max-ply")
+                                .long("max-ply")
+                                .value_name("PLY")
+                                .help("Only keep moves up to this many half-moves into each game (default 20)"),
+                        )
+                        .arg(
+                            Arg::new("min-games")
+                                .long("min-games")
+                                .value_name("GAMES")
+                                .help("Drop moves played in fewer games than this (default 1)"),
+                        )
+                        .arg(
+                            Arg::new("min-elo")
+                                .long("min-elo")
+                                .value_name("ELO")
+                                .help("Skip games unless both players are rated at least this much (default 0)"),
+                        )
+                        .arg(
+                            Arg::new("scores")
+                                .long("scores")
+                                .value_name("WIN,DRAW,LOSS")
+                                .help("Points a move earns per win, draw and loss (default 2,1,0)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("convert")
+                        .about("Convert a book to Polyglot .bin, or to a SAN line listing for any other extension")
+                        .arg(
+                            Arg::new("in")
+                                .short('i')
+                                .long("in")
+                                .value_name("BOOK")
+                                .required(true)
+                                .help("The book to read (.bin, .abk or .ctg)"),
+                        )
+                        .arg(
+                            Arg::new("out")
+                                .short('o')
+                                .long("out")
+                                .value_name("FILE")
+                                .required(true)
+                                .help("Where to write the converted book"),
+                        )
+                        .arg(
+                            Arg::new("max-ply")
+                                .long("max-ply")
+                                .value_name("PLY")
+                                .help("How many half-moves deep to follow the book (default 40)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("probe")
+                        .about("List every book move for a position, optionally walking the book deeper")
+                        .arg(
+                            Arg::new("fen")
+                                .short('f')
+                                .long("fen")
+                                .value_name("FEN")
+                                .help("The position to look up (defaults to the starting position)"),
+                        )
+                        .arg(
+                            Arg::new("book")
+                                .short('b')
+                                .long("book")
+                                .value_name("BOOK")
+                                .action(ArgAction::Append)
+                                .help("A book to probe; repeat to probe several (defaults to assets/books/Perfect2023.bin)"),
+                        )
+                        .arg(
+                            Arg::new("depth")
+                                .short('d')
+                                .long("depth")
+                                .value_name("DEPTH")
+                                .help("Also print the book tree this many half-moves deep"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("tree")
+                        .about("Print a book as an indented move tree")
+                        .arg(
+                            Arg::new("book")
+                                .short('b')
+                                .long("book")
+                                .value_name("BOOK")
+                                .required(true)
+                                .help("The book to read (.bin, .abk or .ctg)"),
+                        )
+                        .arg(
+                            Arg::new("max-ply")
+                                .long("max-ply")
+                                .value_name("PLY")
+                                .help("How many half-moves deep to print (default 6)"),
+                        ),
                 ),
         )
         .subcommand(
             Command::new("interactive")
                 .about("Run the engine in interactive mode"),
         )
+        .subcommand(
+            Command::new("uci")
+                .about("Speak the UCI protocol on standard input and output, for chess GUIs"),
+        )
         .get_matches();
 
         repl(matches);
     }
 
+/// The shipped book, looked up beside the executable and its parent directories (so `cargo run` finds it
+/// from `target/debug`), then in the working directory.
+fn default_book() -> Option<PathBuf> {
+    let exe = env::current_exe().ok();
+    let roots = exe.iter().flat_map(|exe| exe.ancestors().skip(1)).map(PathBuf::from);
+    roots.chain(env::current_dir().ok()).map(|root| root.join(DEFAULT_BOOK)).find(|path| path.is_file())
+}
+
+/// The default book when it can be found; otherwise a warning, and the engine plays without one.
+fn default_books() -> Vec<PathBuf> {
+    match default_book() {
+        Some(path) => vec![path],
+        None => {
+            eprintln!("Warning: the default opening book {} was not found; playing without a book.", DEFAULT_BOOK);
+            Vec::new()
+        }
+    }
+}
+
+/// The `--variant` a subcommand was given, standard chess when there is none.
+fn variant(matches: &ArgMatches) -> types::Variant {
+    matches
+        .get_one::<String>("variant")
+        .map_or(Ok(types::Variant::Standard), |name| name.parse())
+        .unwrap_or_else(|error| panic!("{}", error))
+}
+
 fn repl(matches: ArgMatches) {
     if let Some(matches) = matches.subcommand_matches("play") {
-        let depth = matches.get_one::<&str>("depth").unwrap_or(&"5").parse().unwrap();
-        let color_str = matches.get_one::<&str>("color").unwrap_or(&"w");
-        let player_color = match *color_str {
+        let depth = matches.get_one::<String>("depth").map_or("5", String::as_str).parse().unwrap();
+        let color_str = matches.get_one::<String>("color").map_or("w", String::as_str);
+        let player_color = match color_str {
             "w" => types::Color::White,
             "b" => types::Color::Black,
             "r" => {
@@ -181,15 +416,89 @@ fn repl(matches: ArgMatches) {
 
           println!("Player chose to play as {:?}", player_color);
 
-          let mut book = OpeningBook::new("assets\\books\\Perfect2023.bin");
-          book.load_from_file().expect("Could not load book from file");
-          run_play(depth, player_color, &book);
+          let mut options = BookOptions {
+              enabled: !matches.get_flag("no-book"),
+              books: matches
+                  .get_many::<String>("book")
+                  .map_or_else(default_books, |books| books.map(PathBuf::from).collect()),
+              ..BookOptions::default()
+          };
+          for (arg, option) in [("book-ply", "BookMaxPly"), ("book-min-weight", "BookMinWeight"), ("book-variety", "BookVariety"), ("book-seed", "BookSeed")] {
+              if let Some(value) = matches.get_one::<String>(arg) {
+                  options.set_option(option, value).unwrap_or_else(|error| panic!("{}", error));
+              }
+          }
+
+          let mut books = BookManager::new(options).expect("Could not load opening books");
+          run_play(depth, player_color, &mut books, variant(matches));
     } else if let Some(matches) = matches.subcommand_matches("analyze") {
-        let fen = *matches.get_one::<&str>("fen").expect("FEN string is required");
-        let depth = matches.get_one::<&str>("depth").unwrap_or(&"5").parse().unwrap();
-        run_analyze(fen, depth);
-    } else if let Some(_) = matches.subcommand_matches("interactive") {
+        let fen = matches.get_one::<String>("fen").expect("FEN string is required");
+        let depth = matches.get_one::<String>("depth").map_or("5", String::as_str).parse().unwrap();
+        run_analyze(fen, depth, variant(matches));
+    } else if let Some(matches) = matches.subcommand_matches("perft") {
+        let variant = variant(matches);
+        let start = types::Board::new_variant(variant).to_fen();
+        let fen = matches.get_one::<String>("fen").map_or(start.as_str(), String::as_str);
+        let depth = matches.get_one::<String>("depth").map_or("5", String::as_str).parse().expect("Depth must be a number");
+        match matches.get_one::<String>("chess960") {
+            Some(index) => {
+                let index = index.parse().expect("Chess960 index must be a number");
+                let board = types::Board::chess960(index).expect("Chess960 index must be below 960");
+                run_perft(&board.to_fen(), depth, true, variant);
+            }
+            None => run_perft(fen, depth, false, variant),
+        }
+     } else if let Some(matches) = matches.subcommand_matches("book") {
+        if let Some(matches) = matches.subcommand_matches("build") {
+            let pgn = matches.get_one::<String>("pgn").expect("PGN file is required");
+            let out = matches.get_one::<String>("out").expect("Output file is required");
+            let defaults = BookBuildOptions::default();
+            let number = |name: &str, default: u32| {
+                matches.get_one::<String>(name).map_or(default, |value| value.parse().expect("Expected a number"))
+            };
+            let scores: Vec<u32> = matches
+                .get_one::<String>("scores")
+                .map_or("2,1,0", String::as_str)
+                .split(',')
+                .map(|score| score.trim().parse().expect("Scores must be numbers"))
+                .collect();
+            let [win_score, draw_score, loss_score] = scores[..] else {
+                panic!("Scores must be given as WIN,DRAW,LOSS");
+            };
+
+            let options = BookBuildOptions {
+                max_ply: number("max-ply", defaults.max_ply),
+                min_games: number("min-games", defaults.min_games),
+                min_elo: number("min-elo", defaults.min_elo),
+                win_score,
+                draw_score,
+                loss_score,
+            };
+            run_book_build(pgn, out, options);
+        } else if let Some(matches) = matches.subcommand_matches("convert") {
+            let input = matches.get_one::<String>("in").expect("Input book is required");
+            let out = matches.get_one::<String>("out").expect("Output file is required");
+            let max_ply = matches.get_one::<String>("max-ply").map_or("40", String::as_str).parse().expect("Ply must be a number");
+            run_book_convert(input, out, max_ply);
+        } else if let Some(matches) = matches.subcommand_matches("probe") {
+            let fen = matches.get_one::<String>("fen").map_or(STARTING_FEN, String::as_str);
+            let default = default_book().map_or_else(|| DEFAULT_BOOK.to_string(), |path| path.display().to_string());
+            let books: Vec<&str> = matches
+                .get_many::<String>("book")
+                .map_or_else(|| vec![default.as_str()], |books| books.map(String::as_str).collect());
+            let depth = matches.get_one::<String>("depth").map_or("1", String::as_str).parse().expect("Depth must be a number");
+            run_book_probe(fen, &books, depth);
+        } else if let Some(matches) = matches.subcommand_matches("tree") {
+            let book = matches.get_one::<String>("book").expect("Book file is required");
+            let max_ply = matches.get_one::<String>("max-ply").map_or("6", String::as_str).parse().expect("Ply must be a number");
+            run_book_tree(book, max_ply);
+        }
+    } else if matches.subcommand_matches("interactive").is_some() {
         run_interactive();
+    } else if matches.subcommand_matches("uci").is_some() {
+        if let Err(error) = comms::UciEngine::new().run(io::stdin().lock(), &mut io::stdout()) {
+            eprintln!("UCI session ended: {}", error);
+        }
         } else {
         println!("Welcome to KishMat Chess Engine, By Ahmad Hamdi, Egypt!");
         println!("You can choose to:");
@@ -212,7 +521,7 @@ fn repl(matches: ArgMatches) {
                     io::stdin().read_line(&mut depth_input).expect("Failed to read line");
                     io::stdin().read_line(&mut color_input).expect("Failed to read line");
                     let depth = depth_input.trim().parse().unwrap_or(5);
-                    let color = depth_input.trim();
+                    let color = color_input.trim();
                     let player_color = match color {
                         "w" => types::Color::White,
                         "b" => types::Color::Black,
@@ -226,9 +535,9 @@ fn repl(matches: ArgMatches) {
                         }
                         _ => types::Color::White, // Default to White if invalid input
                     };
-                    let mut book = OpeningBook::new("assets\\books\\Perfect_2010.abk");
-                    book.load_from_file().expect("Could not load book from file");
-                    run_play(depth, player_color, &book);
+                    let options = BookOptions { books: default_books(), ..BookOptions::default() };
+                    let mut books = BookManager::new(options).expect("Could not load opening books");
+                    run_play(depth, player_color, &mut books, types::Variant::Standard);
                     break;
                 }
                 "analyze" => {
@@ -239,7 +548,7 @@ fn repl(matches: ArgMatches) {
                     let mut depth_input = String::new();
                     io::stdin().read_line(&mut depth_input).expect("Failed to read line");
                     let depth = depth_input.trim().parse().unwrap_or(5);
-                    run_analyze(&fen.trim(), depth);
+                    run_analyze(fen.trim(), depth, types::Variant::Standard);
                     break;
                 }
                 "interactive" => {
@@ -0,0 +1,189 @@
+pub(crate) use crate::board::magic::{bishop_attacks, rook_attacks};
+use crate::{Bitboard, Color, Piece};
+
+const NOT_A_FILE: Bitboard = 0xFEFE_FEFE_FEFE_FEFE;
+const NOT_AB_FILE: Bitboard = 0xFCFC_FCFC_FCFC_FCFC;
+const NOT_H_FILE: Bitboard = 0x7F7F_7F7F_7F7F_7F7F;
+const NOT_GH_FILE: Bitboard = 0x3F3F_3F3F_3F3F_3F3F;
+
+const BISHOP_DIRECTIONS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK_DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Squares a knight attacks from each square.
+pub static KNIGHT_ATTACKS: [Bitboard; 64] = leaper_table(Piece::Knight, Color::White);
+/// Squares a king attacks from each square.
+pub static KING_ATTACKS: [Bitboard; 64] = leaper_table(Piece::King, Color::White);
+/// Squares a pawn attacks from each square, indexed by the pawn's colour first.
+pub static PAWN_ATTACKS: [[Bitboard; 64]; 2] = [leaper_table(Piece::Pawn, Color::White), leaper_table(Piece::Pawn, Color::Black)];
+/// Squares a Shatranj ferz, one diagonal step away, attacks from each square.
+pub static FERZ_ATTACKS: [Bitboard; 64] = leaper_table(Piece::Queen, Color::White);
+/// Squares a Shatranj alfil, two diagonal squares away over whatever stands between, attacks from each square.
+pub static ALFIL_ATTACKS: [Bitboard; 64] = leaper_table(Piece::Bishop, Color::White);
+
+/// Builds the attack table of a leaper at compile time. The queen and bishop leap only in Shatranj,
+/// as the ferz and the alfil.
+const fn leaper_table(piece: Piece, color: Color) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        table[square] = match (piece, color) {
+            (Piece::Knight, _) => knight_mask(square),
+            (Piece::King, _) => king_mask(square),
+            (Piece::Pawn, Color::White) => white_pawn_mask(square),
+            (Piece::Pawn, Color::Black) => black_pawn_mask(square),
+            (Piece::Queen, _) => white_pawn_mask(square) | black_pawn_mask(square),
+            (Piece::Bishop, _) => alfil_mask(square),
+            _ => 0,
+        };
+        square += 1;
+    }
+    table
+}
+
+/// Squares attacked by a knight on the given square, masked so jumps never wrap around the board.
+const fn knight_mask(square: usize) -> Bitboard {
+    let bit = 1u64 << square;
+    ((bit << 17) & NOT_A_FILE)
+        | ((bit << 15) & NOT_H_FILE)
+        | ((bit << 10) & NOT_AB_FILE)
+        | ((bit << 6) & NOT_GH_FILE)
+        | ((bit >> 17) & NOT_H_FILE)
+        | ((bit >> 15) & NOT_A_FILE)
+        | ((bit >> 10) & NOT_GH_FILE)
+        | ((bit >> 6) & NOT_AB_FILE)
+}
+
+const fn king_mask(square: usize) -> Bitboard {
+    let bit = 1u64 << square;
+    let sideways = ((bit << 1) & NOT_A_FILE) | ((bit >> 1) & NOT_H_FILE);
+    let row = bit | sideways;
+    sideways | (row << 8) | (row >> 8)
+}
+
+const fn alfil_mask(square: usize) -> Bitboard {
+    let bit = 1u64 << square;
+    ((bit << 18) & NOT_AB_FILE) | ((bit << 14) & NOT_GH_FILE) | ((bit >> 14) & NOT_AB_FILE) | ((bit >> 18) & NOT_GH_FILE)
+}
+
+const fn white_pawn_mask(square: usize) -> Bitboard {
+    let bit = 1u64 << square;
+    ((bit << 7) & NOT_H_FILE) | ((bit << 9) & NOT_A_FILE)
+}
+
+const fn black_pawn_mask(square: usize) -> Bitboard {
+    let bit = 1u64 << square;
+    ((bit >> 9) & NOT_H_FILE) | ((bit >> 7) & NOT_A_FILE)
+}
+
+/// Squares attacked by a knight on the given square.
+#[inline(always)]
+pub(crate) fn knight_attacks(square: usize) -> Bitboard {
+    KNIGHT_ATTACKS[square]
+}
+
+/// Squares attacked by a king on the given square.
+#[inline(always)]
+pub(crate) fn king_attacks(square: usize) -> Bitboard {
+    KING_ATTACKS[square]
+}
+
+/// Squares attacked by a pawn of the given color standing on the given square.
+#[inline(always)]
+pub(crate) fn pawn_attacks(color: Color, square: usize) -> Bitboard {
+    PAWN_ATTACKS[color as usize][square]
+}
+
+/// Squares attacked by a ferz on the given square.
+#[inline(always)]
+pub(crate) fn ferz_attacks(square: usize) -> Bitboard {
+    FERZ_ATTACKS[square]
+}
+
+/// Squares attacked by an alfil on the given square.
+#[inline(always)]
+pub(crate) fn alfil_attacks(square: usize) -> Bitboard {
+    ALFIL_ATTACKS[square]
+}
+
+/// Walks each direction from the square until the edge or the first occupied square (which is included).
+#[inline(always)]
+fn sliding_attacks(square: usize, occupancy: Bitboard, directions: &[(isize, isize); 4]) -> Bitboard {
+    let mut attacks = 0;
+    let (rank, file) = ((square / 8) as isize, (square % 8) as isize);
+
+    for &(rank_step, file_step) in directions {
+        let (mut r, mut f) = (rank + rank_step, file + file_step);
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            let bit = 1u64 << (r * 8 + f);
+            attacks |= bit;
+            if occupancy & bit != 0 {
+                break; // Blocked: the blocker itself is attacked, nothing beyond it
+            }
+            r += rank_step;
+            f += file_step;
+        }
+    }
+
+    attacks
+}
+
+/// Bishop attacks by walking the rays; the reference the magic tables are built from.
+pub(crate) fn bishop_attacks_slow(square: usize, occupancy: Bitboard) -> Bitboard {
+    sliding_attacks(square, occupancy, &BISHOP_DIRECTIONS)
+}
+
+/// Rook attacks by walking the rays; the reference the magic tables are built from.
+pub(crate) fn rook_attacks_slow(square: usize, occupancy: Bitboard) -> Bitboard {
+    sliding_attacks(square, occupancy, &ROOK_DIRECTIONS)
+}
+
+/// Returns the unit step from `from` towards `to` if both lie on a common rank, file or diagonal.
+#[inline(always)]
+fn direction(from: usize, to: usize) -> Option<(isize, isize)> {
+    let rank_diff = (to / 8) as isize - (from / 8) as isize;
+    let file_diff = (to % 8) as isize - (from % 8) as isize;
+
+    if from == to || (rank_diff != 0 && file_diff != 0 && rank_diff.abs() != file_diff.abs()) {
+        return None;
+    }
+
+    Some((rank_diff.signum(), file_diff.signum()))
+}
+
+/// Squares strictly between two aligned squares, or an empty bitboard if they are not aligned.
+#[inline(always)]
+pub(crate) fn between(from: usize, to: usize) -> Bitboard {
+    let Some((rank_step, file_step)) = direction(from, to) else {
+        return 0;
+    };
+
+    let mut squares = 0;
+    let (mut r, mut f) = ((from / 8) as isize + rank_step, (from % 8) as isize + file_step);
+    while (r * 8 + f) as usize != to {
+        squares |= 1u64 << (r * 8 + f);
+        r += rank_step;
+        f += file_step;
+    }
+
+    squares
+}
+
+/// The full edge-to-edge line through two aligned squares, or an empty bitboard if they are not aligned.
+#[inline(always)]
+pub(crate) fn line(from: usize, to: usize) -> Bitboard {
+    let Some((rank_step, file_step)) = direction(from, to) else {
+        return 0;
+    };
+
+    let mut squares = 1u64 << from;
+    for (rank_step, file_step) in [(rank_step, file_step), (-rank_step, -file_step)] {
+        let (mut r, mut f) = ((from / 8) as isize + rank_step, (from % 8) as isize + file_step);
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            squares |= 1u64 << (r * 8 + f);
+            r += rank_step;
+            f += file_step;
+        }
+    }
+
+    squares
+}
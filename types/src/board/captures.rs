@@ -1,32 +1,18 @@
-use crate::{clear_bit, get_lsb, is_bit_set, Board, Color, Piece, Square};
+use std::cmp::Reverse;
+use crate::{is_bit_set, Board, ChessMove, Color, Square};
 
 impl Board{
     
-    /// Generates all legal capture moves for the given color.
-    pub fn generate_captures(&self, color: Color) -> Vec<(Square, Square)> {
-        let mut captures = Vec::new();
-
-        for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King].iter() {
-            let piece_bitboard = self.pieces[self.get_piece_index(*piece, color)];
-
-            let mut piece_positions = piece_bitboard;
-            while piece_positions != 0 {
-                let from_square_index = get_lsb(piece_positions);
-                let from_square = Square::from_index(from_square_index);
-
-                // Generate all potential moves for this piece
-                let possible_moves = self.generate_piece_moves(*piece, from_square, color);
-
-                for piece_move in possible_moves {
-                    if self.is_capture(piece_move.0, color) {
-                        captures.push((from_square, piece_move.0));
-                    }
-                }
-
-                clear_bit(&mut piece_positions, from_square_index);
-            }
-        }
-
+    /// Generates all legal capture moves for the given color with their static exchange scores, the ones
+    /// that win the most first.
+    pub fn generate_captures(&self, color: Color) -> Vec<(ChessMove, i32)> {
+        let mut captures: Vec<(ChessMove, i32)> = self
+            .generate_legal_moves(color)
+            .into_iter()
+            .filter(|mv| mv.is_capture())
+            .map(|mv| (mv, self.see(mv)))
+            .collect();
+        captures.sort_by_key(|&(_, score)| Reverse(score));
         captures
     }
 
@@ -39,23 +25,12 @@ impl Board{
 
     #[inline(always)]
     pub(crate) fn capture_piece(&mut self, square: Square) {
-        let square_index = square.to_index();
-
-        // Check and remove the piece from the board
-        for piece_type in 0..6 {
-            // Check if a white piece is on the square
-            if is_bit_set(self.pieces[piece_type], square_index) {
-                clear_bit(&mut self.pieces[piece_type], square_index);
-                break;
-            }
-            // Check if a black piece is on the square
-            if is_bit_set(self.pieces[piece_type + 6], square_index) {
-                clear_bit(&mut self.pieces[piece_type + 6], square_index);
-                break;
-            }
+        // Remove whichever piece stands on the square
+        if let Some((piece, color)) = self.get_piece_at_square(square) {
+            self.toggle_piece(square, piece, color);
         }
 
         // Update the occupancy bitboards
         self.update_occupancy();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,79 @@
+use crate::board::Board;
+use crate::{ChessMove, Color, MoveFlag, Square};
+
+/// Where the two knights go among the five files left once the bishops and queen are placed.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+impl Board {
+    /// The Chess960 starting position with the given Scharnagl number, from 0 to 959; number 518 is the
+    /// standard starting position.
+    ///
+    /// The number is read as a light-squared bishop file, a dark-squared bishop file, the queen's place
+    /// among the free files and a knight pairing, after which rook, king and rook fill the last three files.
+    pub fn chess960(number: u16) -> Option<Board> {
+        if number >= 960 {
+            return None;
+        }
+
+        let mut back_rank = [None; 8];
+        let mut number = number as usize;
+        back_rank[number % 4 * 2 + 1] = Some('b');
+        number /= 4;
+        back_rank[number % 4 * 2] = Some('b');
+        number /= 4;
+
+        let free_file = |back_rank: &[Option<char>; 8], nth: usize| (0..8).filter(|&file| back_rank[file].is_none()).nth(nth).expect("Enough free files");
+        let queen = free_file(&back_rank, number % 6);
+        back_rank[queen] = Some('q');
+        number /= 6;
+
+        let (first, second) = KNIGHT_PLACEMENTS[number];
+        let (first, second) = (free_file(&back_rank, first), free_file(&back_rank, second));
+        back_rank[first] = Some('n');
+        back_rank[second] = Some('n');
+
+        for piece in ['r', 'k', 'r'] {
+            let file = free_file(&back_rank, 0);
+            back_rank[file] = Some(piece);
+        }
+
+        let black: String = back_rank.iter().map(|piece| piece.expect("Every file is filled")).collect();
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", black, black.to_ascii_uppercase());
+        let mut board = Board::from_fen(&fen).ok()?;
+        board.chess960 = true;
+        Some(board)
+    }
+
+    /// Whether a castling right needs Chess960 rules: its king is off the e-file or its rook off the corner.
+    pub(crate) fn has_chess960_castling(&self) -> bool {
+        const CORNERS: [Square; 4] = [Square::H1, Square::A1, Square::H8, Square::A8];
+        (0..4).any(|index| {
+            let color = if index < 2 { Color::White } else { Color::Black };
+            self.castling_rights & (1 << index) != 0 && (self.castling_rooks[index] != CORNERS[index] || self.king_square(color).file_usize() != 4)
+        })
+    }
+
+    /// Whether the game is played under Chess960 castling notation.
+    #[inline(always)]
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Switches Chess960 castling notation on or off, as the `UCI_Chess960` option does. Positions whose
+    /// kings or castling rooks start off their usual squares turn it on by themselves when loaded from FEN.
+    #[inline(always)]
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    /// Writes a move in UCI notation. Under Chess960 a castle is written as the king taking its own rook,
+    /// since the king's destination alone cannot tell it apart from an ordinary king move.
+    pub fn move_to_uci(&self, mv: ChessMove) -> String {
+        if !self.chess960 || !mv.is_castle() {
+            return mv.to_string();
+        }
+
+        let (king_from, _, rook_from, _) = self.castling_squares(mv.from(), self.side_to_move, mv.flag() == MoveFlag::KingCastle);
+        format!("{}{}", king_from, rook_from)
+    }
+}
@@ -0,0 +1,205 @@
+use crate::board::attacks::pawn_attacks;
+use crate::board::Board;
+use crate::board::{BLACK_KINGSIDE, BLACK_QUEENSIDE, WHITE_KINGSIDE, WHITE_QUEENSIDE};
+use crate::{ChessMove, Color, Piece, Square};
+
+const EN_PASSANT_FLAG: u8 = 0x20;
+const CASTLING_FLAG: u8 = 0x40;
+
+/// CTG stores every position with the side to move as White. Black-to-move positions are
+/// flipped top to bottom with colors swapped, and positions without castling rights are also
+/// mirrored left to right when that puts the king on the kingside.
+#[derive(Clone, Copy)]
+struct Orientation {
+    flip: bool,
+    mirror: bool,
+}
+
+impl Orientation {
+    fn of(board: &Board) -> Self {
+        let flip = board.side_to_move == Color::Black;
+        let king_file = board.king_square(board.side_to_move).file_usize();
+        Orientation { flip, mirror: board.castling_rights == 0 && king_file < 4 }
+    }
+
+    /// Maps a real square to (file, rank) in the stored orientation; the mapping is its own inverse.
+    #[inline(always)]
+    fn coordinates(self, square: Square) -> (usize, usize) {
+        let file = if self.mirror { 7 - square.file_usize() } else { square.file_usize() };
+        let rank = if self.flip { 7 - square.rank_usize() } else { square.rank_usize() };
+        (file, rank)
+    }
+
+    #[inline(always)]
+    fn square(self, file: usize, rank: usize) -> Square {
+        Square::new(if self.flip { 7 - rank } else { rank }, if self.mirror { 7 - file } else { file })
+    }
+}
+
+/// Appends bits most significant first, the way CTG packs positions.
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn push(&mut self, bit: bool) {
+        if self.bits.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().expect("Byte pushed above") |= 0x80 >> (self.bits % 8);
+        }
+        self.bits += 1;
+    }
+
+    fn push_bits(&mut self, value: u8, count: usize) {
+        for shift in (0..count).rev() {
+            self.push(value >> shift & 1 != 0);
+        }
+    }
+}
+
+impl Board {
+    /// Encodes the position the way ChessBase `.ctg` books store it, so it can be matched byte for byte.
+    ///
+    /// The first byte holds the total length plus en passant and castling flags; the board follows
+    /// as Huffman codes file by file from a1, then padding, the en passant file and the castling rights.
+    pub fn ctg_position(&self) -> Vec<u8> {
+        let orientation = Orientation::of(self);
+        let mut writer = BitWriter { bytes: vec![0], bits: 8 };
+
+        for file in 0..8 {
+            for rank in 0..8 {
+                let Some((piece, color)) = self.get_piece_at_square(orientation.square(file, rank)) else {
+                    writer.push(false);
+                    continue;
+                };
+
+                let (code, length) = match piece {
+                    Piece::Pawn => (0b11, 2),
+                    Piece::Rook => (0b1011, 4),
+                    Piece::Bishop => (0b1010, 4),
+                    Piece::Knight => (0b1001, 4),
+                    Piece::Queen => (0b10001, 5),
+                    Piece::King => (0b10000, 5),
+                };
+                writer.push_bits(code, length);
+                writer.push(color != self.side_to_move); // Set for the side not to move, stored as Black
+            }
+        }
+
+        let en_passant_file = self.en_passant.filter(|&square| {
+            let capturers = pawn_attacks(self.side_to_move.opponent(), square.to_index())
+                & self.pieces[self.get_piece_index(Piece::Pawn, self.side_to_move)];
+            capturers != 0
+        });
+        let en_passant_file = en_passant_file.map(|square| orientation.coordinates(square).0 as u8);
+
+        // Castling rights as seen from the stored side, which is always White
+        let rights = if orientation.flip {
+            [BLACK_KINGSIDE, BLACK_QUEENSIDE, WHITE_KINGSIDE, WHITE_QUEENSIDE]
+        } else {
+            [WHITE_KINGSIDE, WHITE_QUEENSIDE, BLACK_KINGSIDE, BLACK_QUEENSIDE]
+        };
+
+        // Padding goes between the board and the trailing fields so that those end on a byte boundary
+        let trailing = if en_passant_file.is_some() { 3 } else { 0 } + if self.castling_rights != 0 { 4 } else { 0 };
+        while !(writer.bits + trailing).is_multiple_of(8) {
+            writer.push(false);
+        }
+
+        if let Some(file) = en_passant_file {
+            writer.push_bits(file, 3);
+        }
+        if self.castling_rights != 0 {
+            for right in rights {
+                writer.push(self.castling_rights & right != 0);
+            }
+        }
+
+        let mut header = writer.bytes.len() as u8;
+        if en_passant_file.is_some() {
+            header |= EN_PASSANT_FLAG;
+        }
+        if self.castling_rights != 0 {
+            header |= CASTLING_FLAG;
+        }
+        writer.bytes[0] = header;
+        writer.bytes
+    }
+
+    /// Decodes a one-byte CTG book move against this position.
+    ///
+    /// CTG names a move by piece kind, which of those pieces it is (counted in encoding order) and a
+    /// step forward and to the right that wraps around the board; pawns reaching the last rank promote to a queen.
+    pub fn move_from_ctg(&self, code: u8) -> Option<ChessMove> {
+        let index = CTG_MOVES.binary_search_by_key(&code, |&(code, ..)| code).ok()?;
+        let (_, piece, nth, forward, right) = CTG_MOVES[index];
+        let orientation = Orientation::of(self);
+
+        let from_square = (0..64)
+            .map(|index| orientation.square(index / 8, index % 8))
+            .filter(|&square| self.get_piece_at_square(square) == Some((piece, self.side_to_move)))
+            .nth(nth as usize - 1)?;
+
+        let (file, rank) = orientation.coordinates(from_square);
+        let to_file = (file as i8 + right).rem_euclid(8) as usize;
+        let to_rank = (rank as i8 + forward).rem_euclid(8) as usize;
+        let to_square = orientation.square(to_file, to_rank);
+
+        let promotion = (piece == Piece::Pawn && to_rank == 7).then_some(Piece::Queen);
+        Some(self.move_from_squares(from_square, to_square, promotion))
+    }
+}
+
+/// CTG move codes: (code, piece, which piece of that kind, steps forward, steps right).
+///
+/// Castling is the king stepping two files. Steps wrap around the board, so four files to the right
+/// and four to the left land on the same file: each bishop and queen has a single code for its
+/// diagonal steps of four squares, and it covers both directions.
+#[rustfmt::skip]
+const CTG_MOVES: [(u8, Piece, u8, i8, i8); 166] = [
+    (0x00, Piece::Pawn, 5, 1, 1), (0x01, Piece::Knight, 2, -1, -2), (0x03, Piece::Queen, 2, 0, 2), (0x04, Piece::Pawn, 2, 1, 0),
+    (0x05, Piece::Queen, 1, 1, 0), (0x06, Piece::Pawn, 4, 1, -1), (0x08, Piece::Queen, 2, 0, 4), (0x09, Piece::Bishop, 2, 6, 6),
+    (0x0a, Piece::King, 1, -1, 0), (0x0c, Piece::Pawn, 1, 1, -1), (0x0d, Piece::Bishop, 1, 3, 3), (0x0e, Piece::Rook, 2, 0, 3),
+    (0x0f, Piece::Knight, 1, -1, -2), (0x12, Piece::Bishop, 1, 7, 7), (0x13, Piece::King, 1, 1, 0), (0x14, Piece::Pawn, 8, 1, 1),
+    (0x15, Piece::Bishop, 1, 5, 5), (0x18, Piece::Pawn, 7, 1, 0), (0x1a, Piece::Queen, 2, 6, 0), (0x1b, Piece::Bishop, 1, 1, -1),
+    (0x1d, Piece::Bishop, 2, 7, -7), (0x21, Piece::Rook, 2, 0, 7), (0x22, Piece::Bishop, 2, 2, -2), (0x23, Piece::Queen, 2, 6, 6),
+    (0x24, Piece::Pawn, 8, 1, -1), (0x26, Piece::Bishop, 1, 7, -7), (0x27, Piece::Pawn, 3, 1, -1), (0x28, Piece::Queen, 1, 5, 5),
+    (0x29, Piece::Queen, 1, 0, 6), (0x2a, Piece::Knight, 2, -2, 1), (0x2d, Piece::Pawn, 6, 1, 1), (0x2e, Piece::Bishop, 1, 1, 1),
+    (0x2f, Piece::Queen, 1, 0, 1), (0x30, Piece::Knight, 2, -2, -1), (0x31, Piece::Queen, 1, 0, 3), (0x32, Piece::Bishop, 2, 5, 5),
+    (0x34, Piece::Knight, 1, 2, 1), (0x36, Piece::Knight, 1, 1, 2), (0x37, Piece::Queen, 1, 4, 0), (0x38, Piece::Queen, 2, 4, -4),
+    (0x39, Piece::Queen, 1, 0, 5), (0x3a, Piece::Bishop, 1, 6, 6), (0x3b, Piece::Queen, 2, 5, -5), (0x3c, Piece::Bishop, 1, 5, -5),
+    (0x41, Piece::Queen, 2, 5, 5), (0x42, Piece::Queen, 1, 7, -7), (0x44, Piece::King, 1, -1, 1), (0x45, Piece::Queen, 1, 3, 3),
+    (0x4a, Piece::Pawn, 8, 2, 0), (0x4b, Piece::Queen, 1, 5, -5), (0x4c, Piece::Knight, 2, 2, 1), (0x4d, Piece::Queen, 2, 1, 0),
+    (0x50, Piece::Rook, 1, 6, 0), (0x52, Piece::Rook, 1, 0, 6), (0x54, Piece::Bishop, 2, 1, -1), (0x55, Piece::Pawn, 3, 1, 0),
+    (0x5c, Piece::Pawn, 7, 1, 1), (0x5f, Piece::Pawn, 5, 2, 0), (0x61, Piece::Queen, 1, 6, 6), (0x62, Piece::Pawn, 2, 2, 0),
+    (0x63, Piece::Queen, 2, 7, -7), (0x66, Piece::Bishop, 1, 3, -3), (0x67, Piece::King, 1, 1, 1), (0x69, Piece::Rook, 2, 7, 0),
+    (0x6a, Piece::Bishop, 1, 4, 4), (0x6b, Piece::King, 1, 0, 2), (0x6e, Piece::Rook, 1, 0, 5), (0x6f, Piece::Queen, 2, 7, 7),
+    (0x72, Piece::Bishop, 2, 7, 7), (0x74, Piece::Queen, 1, 0, 2), (0x79, Piece::Bishop, 2, 6, -6), (0x7a, Piece::Rook, 1, 3, 0),
+    (0x7b, Piece::Rook, 2, 6, 0), (0x7c, Piece::Pawn, 3, 1, 1), (0x7d, Piece::Rook, 2, 1, 0), (0x7e, Piece::Queen, 1, 3, -3),
+    (0x7f, Piece::Rook, 1, 0, 1), (0x80, Piece::Queen, 1, 6, -6), (0x81, Piece::Rook, 1, 1, 0), (0x82, Piece::Pawn, 6, 1, -1),
+    (0x85, Piece::Knight, 1, 2, -1), (0x86, Piece::Rook, 1, 0, 7), (0x87, Piece::Rook, 1, 5, 0), (0x8a, Piece::Knight, 1, -2, 1),
+    (0x8b, Piece::Pawn, 1, 1, 1), (0x8c, Piece::King, 1, -1, -1), (0x8e, Piece::Queen, 2, 2, -2), (0x8f, Piece::Queen, 1, 0, 7),
+    (0x92, Piece::Queen, 2, 1, 1), (0x94, Piece::Queen, 1, 3, 0), (0x96, Piece::Pawn, 2, 1, 1), (0x97, Piece::King, 1, 0, -1),
+    (0x98, Piece::Rook, 1, 0, 3), (0x99, Piece::Rook, 1, 4, 0), (0x9a, Piece::Queen, 1, 6, 0), (0x9b, Piece::Pawn, 3, 2, 0),
+    (0x9d, Piece::Queen, 1, 2, 0), (0x9f, Piece::Bishop, 2, 4, -4), (0xa0, Piece::Queen, 2, 3, 0), (0xa2, Piece::Queen, 1, 2, 2),
+    (0xa3, Piece::Pawn, 8, 1, 0), (0xa5, Piece::Rook, 2, 5, 0), (0xa9, Piece::Rook, 2, 0, 2), (0xab, Piece::Queen, 2, 6, -6),
+    (0xad, Piece::Rook, 2, 0, 4), (0xae, Piece::Queen, 2, 3, 3), (0xb0, Piece::Queen, 2, 4, 0), (0xb1, Piece::Pawn, 6, 2, 0),
+    (0xb2, Piece::Bishop, 1, 6, -6), (0xb5, Piece::Rook, 2, 0, 5), (0xb7, Piece::Queen, 1, 5, 0), (0xb9, Piece::Bishop, 2, 3, 3),
+    (0xbb, Piece::Pawn, 5, 1, 0), (0xbc, Piece::Queen, 2, 0, 5), (0xbd, Piece::Queen, 2, 2, 0), (0xbe, Piece::King, 1, 0, 1),
+    (0xc1, Piece::Bishop, 1, 2, 2), (0xc2, Piece::Bishop, 2, 2, 2), (0xc3, Piece::Bishop, 1, 2, -2), (0xc4, Piece::Rook, 2, 0, 1),
+    (0xc5, Piece::Rook, 2, 4, 0), (0xc6, Piece::Queen, 2, 5, 0), (0xc7, Piece::Pawn, 7, 1, -1), (0xc8, Piece::Pawn, 7, 2, 0),
+    (0xc9, Piece::Queen, 2, 7, 0), (0xca, Piece::Bishop, 2, 3, -3), (0xcb, Piece::Pawn, 6, 1, 0), (0xcc, Piece::Bishop, 2, 5, -5),
+    (0xcd, Piece::Rook, 1, 0, 2), (0xcf, Piece::Pawn, 4, 1, 0), (0xd1, Piece::Pawn, 2, 1, -1), (0xd2, Piece::Knight, 2, 1, 2),
+    (0xd3, Piece::Knight, 2, 1, -2), (0xd7, Piece::Queen, 1, 1, -1), (0xd8, Piece::Rook, 2, 0, 6), (0xd9, Piece::Queen, 1, 2, -2),
+    (0xda, Piece::Knight, 1, -2, -1), (0xdb, Piece::Pawn, 1, 2, 0), (0xde, Piece::Pawn, 5, 1, -1), (0xdf, Piece::King, 1, 1, -1),
+    (0xe0, Piece::Knight, 2, -1, 2), (0xe1, Piece::Rook, 1, 7, 0), (0xe3, Piece::Rook, 2, 3, 0), (0xe5, Piece::Queen, 1, 0, 4),
+    (0xe6, Piece::Pawn, 4, 2, 0), (0xe7, Piece::Queen, 1, 4, 4), (0xe8, Piece::Rook, 1, 2, 0), (0xe9, Piece::Knight, 1, 1, -2),
+    (0xeb, Piece::Pawn, 4, 1, 1), (0xec, Piece::Pawn, 1, 1, 0), (0xed, Piece::Queen, 1, 7, 7), (0xee, Piece::Queen, 2, 1, -1),
+    (0xef, Piece::Rook, 1, 0, 4), (0xf0, Piece::Queen, 2, 0, 7), (0xf1, Piece::Queen, 1, 1, 1), (0xf3, Piece::Knight, 2, 2, -1),
+    (0xf4, Piece::Rook, 2, 2, 0), (0xf5, Piece::Bishop, 2, 1, 1), (0xf6, Piece::King, 1, 0, -2), (0xf7, Piece::Knight, 1, -1, 2),
+    (0xf8, Piece::Queen, 2, 0, 1), (0xf9, Piece::Queen, 2, 0, 6), (0xfa, Piece::Queen, 2, 0, 3), (0xfb, Piece::Queen, 2, 2, 2),
+    (0xfd, Piece::Queen, 1, 7, 0), (0xfe, Piece::Queen, 2, 3, -3),
+];
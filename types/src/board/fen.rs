@@ -1,68 +1,502 @@
+use std::fmt;
 use std::str::FromStr;
-use crate::board::Board;
-use crate::{set_bit, Color, Piece, Square};
-
-impl Board{
-        pub fn from_fen(fen: &str) -> Option<Self> {
-            let mut board = Self::default(); // Start with a default board
-    
-            let parts: Vec<&str> = fen.split_whitespace().collect();
-            assert_eq!(parts.len(), 6, "Invalid FEN string");
-    
-            // 1. Piece placement
-            let rows: Vec<&str> = parts[0].split('/').collect();
-            for (rank, row) in rows.iter().enumerate() {
-                let mut file = 0;
-                for ch in row.chars() {
-                    match ch {
-                        '1'..='8' => file += ch.to_digit(10)? as usize,
-                        'r' => board.set_piece(Square::new(rank, file), Piece::Rook, Color::Black),
-                        'n' => board.set_piece(Square::new(rank, file), Piece::Knight, Color::Black),
-                        'b' => board.set_piece(Square::new(rank, file), Piece::Bishop, Color::Black),
-                        'q' => board.set_piece(Square::new(rank, file), Piece::Queen, Color::Black),
-                        'k' => board.set_piece(Square::new(rank, file), Piece::King, Color::Black),
-                        'p' => board.set_piece(Square::new(rank, file), Piece::Pawn, Color::Black),
-                        'R' => board.set_piece(Square::new(rank, file), Piece::Rook, Color::White),
-                        'N' => board.set_piece(Square::new(rank, file), Piece::Knight, Color::White),
-                        'B' => board.set_piece(Square::new(rank, file), Piece::Bishop, Color::White),
-                        'Q' => board.set_piece(Square::new(rank, file), Piece::Queen, Color::White),
-                        'K' => board.set_piece(Square::new(rank, file), Piece::King, Color::White),
-                        'P' => board.set_piece(Square::new(rank, file), Piece::Pawn, Color::White),
-                        _ => panic!("Invalid character in FEN"),
-                    }
-                    file += 1;
+use crate::board::{castling_right, Board, SanError};
+use crate::{set_bit, ChessMove, Color, Piece, Square, Zobrist};
+
+/// Why a FEN string could not be loaded. Ranks are numbered as on the board, 8 down to 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// A FEN has six space-separated fields; the two move counters may be left off.
+    WrongFieldCount(usize),
+    /// The placement field must list exactly eight ranks.
+    WrongRankCount(usize),
+    /// A rank describes more or fewer than eight squares.
+    BadRankLength { rank: usize, squares: usize },
+    BadPiece(char),
+    BadSideToMove(String),
+    /// The castling field is malformed, or grants a right whose king or rook is not at home.
+    BadCastling(String),
+    /// Not a square, or not on the rank a pawn of the side that just moved skips over.
+    BadEnPassant(String),
+    BadHalfmoveClock(String),
+    BadFullmoveNumber(String),
+    /// Each side needs exactly one king.
+    BadKingCount { color: Color, kings: usize },
+    /// Pawns can never stand on the first or eighth rank.
+    PawnOnBackRank(Square),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(fields) => write!(f, "expected 6 fields, found {}", fields),
+            FenError::WrongRankCount(ranks) => write!(f, "expected 8 ranks, found {}", ranks),
+            FenError::BadRankLength { rank, squares } => write!(f, "rank {} describes {} squares instead of 8", rank, squares),
+            FenError::BadPiece(ch) => write!(f, "'{}' is not a piece", ch),
+            FenError::BadSideToMove(field) => write!(f, "side to move '{}' is not 'w' or 'b'", field),
+            FenError::BadCastling(field) => write!(f, "invalid castling field '{}'", field),
+            FenError::BadEnPassant(field) => write!(f, "invalid en passant square '{}'", field),
+            FenError::BadHalfmoveClock(field) => write!(f, "invalid halfmove clock '{}'", field),
+            FenError::BadFullmoveNumber(field) => write!(f, "invalid fullmove number '{}'", field),
+            FenError::BadKingCount { color, kings } => write!(f, "{:?} has {} kings", color, kings),
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on {}", square),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Board {
+    /// Loads a position from Forsyth-Edwards Notation. The halfmove clock and fullmove number may be
+    /// omitted, as they are in EPD, and default to `0 1`.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let mut board = Self { pieces: [0; 12], ..Self::default() };
+
+        // 1. Piece placement, rank 8 first
+        let rows: Vec<&str> = fields[0].split('/').collect();
+        if rows.len() != 8 {
+            return Err(FenError::WrongRankCount(rows.len()));
+        }
+        for (row_index, row) in rows.iter().enumerate() {
+            let rank = 7 - row_index;
+            let mut file = 0;
+            for ch in row.chars() {
+                if let Some(empty @ 1..=8) = ch.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+
+                let piece = Piece::from_char(ch).ok_or(FenError::BadPiece(ch))?;
+                let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+                if file < 8 {
+                    board.set_piece(Square::new(rank, file), piece, color);
                 }
+                file += 1;
+            }
+            if file != 8 {
+                return Err(FenError::BadRankLength { rank: rank + 1, squares: file });
+            }
+        }
+        board.update_occupancy();
+
+        for color in [Color::White, Color::Black] {
+            let kings = board.piece_count(Piece::King, color);
+            if kings != 1 {
+                return Err(FenError::BadKingCount { color, kings });
+            }
+        }
+        let pawns = board.pieces[board.get_piece_index(Piece::Pawn, Color::White)] | board.pieces[board.get_piece_index(Piece::Pawn, Color::Black)];
+        let back_ranks = 0xFF00_0000_0000_00FF;
+        if pawns & back_ranks != 0 {
+            return Err(FenError::PawnOnBackRank(Square::from_index((pawns & back_ranks).trailing_zeros() as usize)));
         }
 
         // 2. Active color
-        board.side_to_move = if parts[1] == "w" { Color::White } else { Color::Black };
+        board.side_to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            field => return Err(FenError::BadSideToMove(field.to_string())),
+        };
 
         // 3. Castling availability
-        board.castling_rights = 0;
-        if parts[2].contains('K') { board.castling_rights |= 1 << 0; }
-        if parts[2].contains('Q') { board.castling_rights |= 1 << 1; }
-        if parts[2].contains('k') { board.castling_rights |= 1 << 2; }
-        if parts[2].contains('q') { board.castling_rights |= 1 << 3; }
-
-        // 4. En passant target square
-        board.en_passant = if parts[3] != "-" {
-            Some(Square::from_str(parts[3]).unwrap())
-        } else {
-            None
+        (board.castling_rights, board.castling_rooks) = board.parse_castling(fields[2]).ok_or_else(|| FenError::BadCastling(fields[2].to_string()))?;
+        board.chess960 = board.has_chess960_castling();
+
+        // 4. En passant target square, which sits behind a pawn of the side that just moved
+        board.en_passant = match fields[3] {
+            "-" => None,
+            field => {
+                let (rank, pawn_rank) = if board.side_to_move == Color::White { (5, 4) } else { (2, 3) };
+                let square = Square::from_str(field).ok().filter(|square| square.rank_usize() == rank);
+                let pawn = square.map(|square| Square::new(pawn_rank, square.file_usize()));
+                match (square, pawn) {
+                    (Some(square), Some(pawn)) if board.get_piece_at_square(pawn) == Some((Piece::Pawn, board.side_to_move.opponent())) => Some(square),
+                    _ => return Err(FenError::BadEnPassant(field.to_string())),
+                }
+            }
         };
 
-        // 5. Halfmove clock
-        board.halfmove_clock = parts[4].parse().unwrap();
+        // 5 and 6. Halfmove clock and fullmove number
+        if fields.len() == 6 {
+            board.halfmove_clock = fields[4].parse().map_err(|_| FenError::BadHalfmoveClock(fields[4].to_string()))?;
+            board.fullmove_number = fields[5]
+                .parse()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or_else(|| FenError::BadFullmoveNumber(fields[5].to_string()))?;
+        } else {
+            board.halfmove_clock = 0;
+            board.fullmove_number = 1;
+        }
+
+        board.hash = board.compute_zobrist_hash(Zobrist::global());
+        board.checkers = board.find_checkers();
+
+        Ok(board)
+    }
+
+    /// Writes the position as a six-field FEN string.
+    pub fn to_fen(&self) -> String {
+        format!("{} {} {}", self.fen_position(), self.halfmove_clock, self.fullmove_number)
+    }
+
+    /// The first four FEN fields, which are all of the position EPD keeps.
+    fn fen_position(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.get_piece_at_square(Square::new(rank, file)) {
+                    Some((piece, color)) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let ch = piece.to_char();
+                        fen.push(if color == Color::White { ch } else { ch.to_ascii_lowercase() });
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(if self.side_to_move == Color::White { " w " } else { " b " });
+
+        fen.push_str(&self.castling_field(false));
+
+        match self.en_passant {
+            Some(square) => fen.push_str(&format!(" {}", square)),
+            None => fen.push_str(" -"),
+        }
+        fen
+    }
+
+    /// The same as `to_fen`, but with the castling rights written as Shredder-FEN rook files (`HAha`).
+    pub fn to_shredder_fen(&self) -> String {
+        let mut fields: Vec<String> = self.to_fen().split(' ').map(str::to_string).collect();
+        fields[2] = self.castling_field(true);
+        fields.join(" ")
+    }
+
+    /// The castling field, kingside before queenside and White before Black. X-FEN writes `KQkq` for
+    /// the outermost rook on each side and the rook's file only when another rook stands further out;
+    /// Shredder-FEN always writes the file.
+    fn castling_field(&self, shredder: bool) -> String {
+        let mut field = String::new();
+        for (index, &rook) in self.castling_rooks.iter().enumerate() {
+            if self.castling_rights & (1 << index) == 0 {
+                continue;
+            }
+
+            let (color, kingside) = (if index < 2 { Color::White } else { Color::Black }, index % 2 == 0);
+            let mut beyond = if kingside { rook.file_usize() + 1..8 } else { 0..rook.file_usize() };
+            let outermost = !beyond.any(|file| self.get_piece_at_square(Square::new(rook.rank_usize(), file)) == Some((Piece::Rook, color)));
+            let symbol = match (shredder || !outermost, kingside) {
+                (true, _) => (b'A' + rook.file_usize() as u8) as char,
+                (false, true) => 'K',
+                (false, false) => 'Q',
+            };
+            field.push(if color == Color::White { symbol } else { symbol.to_ascii_lowercase() });
+        }
+
+        if field.is_empty() {
+            field.push('-');
+        }
+        field
+    }
+
+    /// Castling rights from the FEN field with the square of each right's rook, each right at most once.
+    ///
+    /// `K` and `Q` name the outermost rook on that side of the king, as in X-FEN, while a file letter as
+    /// in Shredder-FEN (`HAha`) names the rook directly, which Chess960 needs when two rooks share a side.
+    /// The king must be on its back rank and the rook beside it there.
+    fn parse_castling(&self, field: &str) -> Option<(u8, [Square; 4])> {
+        let mut rooks = self.castling_rooks;
+        if field == "-" {
+            return Some((0, rooks));
+        }
+
+        let mut rights = 0;
+        for ch in field.chars() {
+            let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let rank = if color == Color::White { 0 } else { 7 };
+            let king = self.king_square(color);
+            if king.rank_usize() != rank {
+                return None;
+            }
+            let is_rook = |file: &usize| self.get_piece_at_square(Square::new(rank, *file)) == Some((Piece::Rook, color));
 
-        // 6. Fullmove number
-        board.fullmove_number = parts[5].parse().unwrap();
+            let rook_file = match ch.to_ascii_lowercase() {
+                'k' => (king.file_usize() + 1..8).rev().find(is_rook)?,
+                'q' => (0..king.file_usize()).find(is_rook)?,
+                file @ 'a'..='h' => Some(file as usize - 'a' as usize).filter(is_rook)?,
+                _ => return None,
+            };
+            if rook_file == king.file_usize() {
+                return None;
+            }
 
-        Some(board)
+            let right = castling_right(color, rook_file > king.file_usize());
+            if rights & right != 0 {
+                return None;
+            }
+            rights |= right;
+            rooks[right.trailing_zeros() as usize] = Square::new(rank, rook_file);
+        }
+        Some((rights, rooks))
     }
 
     fn set_piece(&mut self, square: Square, piece: Piece, color: Color) {
         let piece_index = self.get_piece_index(piece, color);
         set_bit(&mut self.pieces[piece_index], square.to_index());
-        self.update_occupancy();
     }
-}
\ No newline at end of file
+}
+
+/// Why an EPD record could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpdError {
+    /// The four position fields are not valid FEN.
+    Fen(FenError),
+    /// An operation is not an opcode with its operands closed by `;`.
+    MalformedOperation(String),
+    /// A move operand of `bm`, `am` or `pv` that is not legal where it is played.
+    Move { opcode: String, error: SanError },
+    /// An operand of `ce`, `acd`, `hmvc` or `fmvn` that is not a number.
+    BadNumber { opcode: String, operand: String },
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::Fen(error) => write!(f, "{}", error),
+            EpdError::MalformedOperation(text) => write!(f, "malformed operation '{}'", text),
+            EpdError::Move { opcode, error } => write!(f, "{}: {}", opcode, error),
+            EpdError::BadNumber { opcode, operand } => write!(f, "{}: '{}' is not a number", opcode, operand),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// One line of an Extended Position Description file: a position and the operations attached to it.
+///
+/// The opcodes test suites and analysis use are read into their own fields, with moves resolved against
+/// the position; any other opcode is kept in `other` with its operands as written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Epd {
+    /// The position, with its move counters taken from the `hmvc` and `fmvn` operations when present.
+    pub board: Board,
+    /// `id`: the record's name, such as `"WAC.001"`.
+    pub id: Option<String>,
+    /// `bm`: the best moves.
+    pub best_moves: Vec<ChessMove>,
+    /// `am`: moves to avoid.
+    pub avoid_moves: Vec<ChessMove>,
+    /// `c0` to `c9`: free-form comments.
+    pub comments: [Option<String>; 10],
+    /// `ce`: the evaluation in centipawns, from the side to move's point of view.
+    pub centipawns: Option<i32>,
+    /// `acd`: the depth the analysis reached.
+    pub depth: Option<u32>,
+    /// `pv`: the predicted line, starting from the position.
+    pub pv: Vec<ChessMove>,
+    pub other: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    pub fn parse(line: &str) -> Result<Epd, EpdError> {
+        let line = line.trim();
+        let mut rest = line;
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        let mut epd = Epd { board: Board::from_fen(&fields.join(" ")).map_err(EpdError::Fen)?, ..Epd::default() };
+        for (opcode, operands) in parse_operations(rest)? {
+            epd.apply(opcode, operands)?;
+        }
+        Ok(epd)
+    }
+
+    fn apply(&mut self, opcode: String, operands: Vec<String>) -> Result<(), EpdError> {
+        let resolve = |board: &Board, san: &String| board.parse_san(san).map_err(|error| EpdError::Move { opcode: opcode.clone(), error });
+
+        match opcode.as_str() {
+            "id" => self.id = operands.into_iter().next(),
+            "bm" => self.best_moves = operands.iter().map(|san| resolve(&self.board, san)).collect::<Result<_, _>>()?,
+            "am" => self.avoid_moves = operands.iter().map(|san| resolve(&self.board, san)).collect::<Result<_, _>>()?,
+            "ce" => self.centipawns = Some(number(&opcode, &operands)?),
+            "acd" => self.depth = Some(number(&opcode, &operands)?),
+            "hmvc" => self.board.halfmove_clock = number(&opcode, &operands)?,
+            "fmvn" => {
+                // Held to the same rule as the FEN field it stands in for
+                self.board.fullmove_number = Some(number(&opcode, &operands)?)
+                    .filter(|&number| number > 0)
+                    .ok_or_else(|| EpdError::Fen(FenError::BadFullmoveNumber(operands.join(" "))))?;
+            }
+            "pv" => {
+                let mut board = self.board;
+                self.pv.clear();
+                for san in &operands {
+                    let mv = resolve(&board, san)?;
+                    board.make_move(mv);
+                    self.pv.push(mv);
+                }
+            }
+            _ => match comment_index(&opcode) {
+                Some(index) => self.comments[index] = operands.into_iter().next(),
+                None => self.other.push((opcode, operands)),
+            },
+        }
+        Ok(())
+    }
+
+    /// Writes the record back as one EPD line, moves in SAN and the move counters as `hmvc` and `fmvn`
+    /// when they are not those of a fresh position.
+    pub fn to_epd(&self) -> String {
+        let mut epd = self.board.fen_position();
+        let mut push = |opcode: &str, operands: &[String]| {
+            epd.push(' ');
+            epd.push_str(opcode);
+            for operand in operands {
+                epd.push(' ');
+                epd.push_str(operand);
+            }
+            epd.push(';');
+        };
+        let quoted = |text: &str| format!("\"{}\"", text.replace('"', "'"));
+        let sans = |moves: &[ChessMove]| moves.iter().map(|&mv| self.board.move_to_san(mv)).collect::<Vec<_>>();
+
+        if !self.best_moves.is_empty() {
+            push("bm", &sans(&self.best_moves));
+        }
+        if !self.avoid_moves.is_empty() {
+            push("am", &sans(&self.avoid_moves));
+        }
+        if let Some(id) = &self.id {
+            push("id", &[quoted(id)]);
+        }
+        for (index, comment) in self.comments.iter().enumerate() {
+            if let Some(comment) = comment {
+                push(&format!("c{}", index), &[quoted(comment)]);
+            }
+        }
+        if let Some(centipawns) = self.centipawns {
+            push("ce", &[centipawns.to_string()]);
+        }
+        if let Some(depth) = self.depth {
+            push("acd", &[depth.to_string()]);
+        }
+        if !self.pv.is_empty() {
+            let mut board = self.board;
+            let line: Vec<String> = self
+                .pv
+                .iter()
+                .map(|&mv| {
+                    let san = board.move_to_san(mv);
+                    board.make_move(mv);
+                    san
+                })
+                .collect();
+            push("pv", &line);
+        }
+        if self.board.halfmove_clock != 0 || self.board.fullmove_number != 1 {
+            push("hmvc", &[self.board.halfmove_clock.to_string()]);
+            push("fmvn", &[self.board.fullmove_number.to_string()]);
+        }
+        for (opcode, operands) in &self.other {
+            let operands: Vec<String> = operands
+                .iter()
+                .map(|operand| if operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';') { quoted(operand) } else { operand.clone() })
+                .collect();
+            push(opcode, &operands);
+        }
+        epd
+    }
+}
+
+/// The single numeric operand of an operation.
+fn number<T: FromStr>(opcode: &str, operands: &[String]) -> Result<T, EpdError> {
+    let operand = operands.first().map_or("", String::as_str);
+    operand.parse().map_err(|_| EpdError::BadNumber { opcode: opcode.to_string(), operand: operand.to_string() })
+}
+
+/// The index of a `c0` to `c9` comment opcode.
+fn comment_index(opcode: &str) -> Option<usize> {
+    match opcode.as_bytes() {
+        &[b'c', digit @ b'0'..=b'9'] => Some((digit - b'0') as usize),
+        _ => None,
+    }
+}
+
+/// Splits the operations after the position fields into opcodes and their operands, unquoting strings.
+/// The `;` closing the last operation may be left off.
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut operations = Vec::new();
+    let mut chars = text.trim().chars().peekable();
+
+    while chars.peek().is_some() {
+        let mut words: Vec<String> = Vec::new();
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => break,
+                '"' => {
+                    let mut word = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => word.push(c),
+                            None => return Err(EpdError::MalformedOperation(text.trim().to_string())),
+                        }
+                    }
+                    words.push(word);
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let mut word = c.to_string();
+                    while let Some(&c) = chars.peek().filter(|&&c| !c.is_whitespace() && c != ';' && c != '"') {
+                        word.push(c);
+                        chars.next();
+                    }
+                    words.push(word);
+                }
+            }
+        }
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+
+        let valid_opcode = words.first().is_some_and(|opcode| {
+            opcode.len() <= 14 && opcode.starts_with(|c: char| c.is_ascii_alphabetic()) && opcode.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        if !valid_opcode {
+            return Err(EpdError::MalformedOperation(text.trim().to_string()));
+        }
+        let opcode = words.remove(0);
+        operations.push((opcode, words));
+    }
+    Ok(operations)
+}
+
+impl Board {
+    /// Loads the position of an EPD record, move counters included.
+    pub fn from_epd(epd: &str) -> Result<Self, EpdError> {
+        Epd::parse(epd).map(|epd| epd.board)
+    }
+}
@@ -1,4 +1,71 @@
-use crate::{Board, Color};
+use std::fmt;
+use crate::{count_bits, Bitboard, Board, Color, Piece, Variant};
+
+/// The light squares (b1, d1, ... g8; bit 0 is a1, which is dark), which tell same-coloured bishops apart.
+const LIGHT_SQUARES: Bitboard = 0x55AA_55AA_55AA_55AA;
+
+/// Why a game is over. The repetition and move-count draws come in two strengths: the first may be
+/// claimed by a player, the second ends the game on the spot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    Checkmate { winner: Color },
+    Stalemate,
+    /// Shatranj: the side that stalemates its opponent wins.
+    StalemateWin { winner: Color },
+    /// Shatranj: the side that captures everything but the opponent's king wins, unless the opponent
+    /// can do the same straight back.
+    BareKing { winner: Color },
+    /// The position has occurred three times with the same side to move.
+    ThreefoldRepetition,
+    /// The position has occurred five times with the same side to move.
+    FivefoldRepetition,
+    /// Fifty moves by each side without a capture or a pawn move.
+    FiftyMoveRule,
+    /// Seventy-five moves by each side without a capture or a pawn move.
+    SeventyFiveMoveRule,
+    /// Neither side has the material left to checkmate.
+    InsufficientMaterial,
+}
+
+impl GameOutcome {
+    #[inline(always)]
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            GameOutcome::Checkmate { winner } | GameOutcome::StalemateWin { winner } | GameOutcome::BareKing { winner } => Some(winner),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_draw(self) -> bool {
+        self.winner().is_none()
+    }
+
+    /// The value of the PGN `Result` tag for a game that ended this way.
+    pub fn result(self) -> &'static str {
+        match self.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameOutcome::Checkmate { winner } => write!(f, "{:?} wins by checkmate", winner),
+            GameOutcome::Stalemate => write!(f, "draw by stalemate"),
+            GameOutcome::StalemateWin { winner } => write!(f, "{:?} wins by stalemate", winner),
+            GameOutcome::BareKing { winner } => write!(f, "{:?} wins by baring the king", winner),
+            GameOutcome::ThreefoldRepetition => write!(f, "draw by threefold repetition"),
+            GameOutcome::FivefoldRepetition => write!(f, "draw by fivefold repetition"),
+            GameOutcome::FiftyMoveRule => write!(f, "draw by the fifty-move rule"),
+            GameOutcome::SeventyFiveMoveRule => write!(f, "draw by the seventy-five-move rule"),
+            GameOutcome::InsufficientMaterial => write!(f, "draw by insufficient material"),
+        }
+    }
+}
 
 pub struct GameState;
 
@@ -14,15 +81,92 @@ impl GameState {
     }
 
     pub fn is_in_check(board: &Board, color: Color) -> bool {
-        let king_square = board.king_square(color);
-        let opponent_color = color.opponent();
-
-        // Check if any opponent's move attacks the king's square
-        let opponent_moves = board.generate_legal_moves(opponent_color);
-        opponent_moves.iter().any(|&(_, to_square)| to_square == king_square)
+        if color == board.side_to_move {
+            return board.checkers() != 0;
+        }
+        board.is_square_attacked(board.king_square(color), color.opponent())
     }
-    
+
     pub fn is_game_over(board: &Board, color: Color) -> bool {
         Self::is_checkmate(board, color) || Self::is_stalemate(board, color)
     }
+
+    /// How often the position has occurred, itself included, given the hashes of the positions before it
+    /// oldest first. Only the positions since the last capture or pawn move can match, so only those are read.
+    pub fn repetitions(board: &Board, history: &[u64]) -> usize {
+        let reversible = history.len().min(board.halfmove_clock as usize);
+        1 + history[history.len() - reversible..].iter().filter(|&&hash| hash == board.hash).count()
+    }
+
+    /// Whether no sequence of legal moves can end in checkmate: bare kings, a lone minor piece, or
+    /// bishops that all stand on squares of one colour. In Shatranj any piece can still win by baring
+    /// the other king, so only two bare kings are a draw.
+    pub fn has_insufficient_material(board: &Board) -> bool {
+        if board.variant() == Variant::Shatranj {
+            return board.has_bare_king(Color::White) && board.has_bare_king(Color::Black);
+        }
+
+        let pieces = |piece: Piece| board.pieces[board.get_piece_index(piece, Color::White)] | board.pieces[board.get_piece_index(piece, Color::Black)];
+        if pieces(Piece::Pawn) | pieces(Piece::Rook) | pieces(Piece::Queen) != 0 {
+            return false;
+        }
+
+        let (knights, bishops) = (pieces(Piece::Knight), pieces(Piece::Bishop));
+        count_bits(knights | bishops) <= 1 || (knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+    }
+
+    /// Who has won by Shatranj's bare-king rule, if anyone. A side left with a bare king gets one move
+    /// to take the opponent's last piece in return, which leaves two bare kings and a draw; a side that
+    /// could not, or did not, has lost.
+    pub fn bare_king_winner(board: &Board) -> Option<Color> {
+        if board.variant() != Variant::Shatranj {
+            return None;
+        }
+
+        let to_move = board.side_to_move;
+        let opponent = to_move.opponent();
+        match (board.has_bare_king(to_move), board.has_bare_king(opponent)) {
+            (true, false) => {
+                // The king cannot be taken, so any capture of a lone piece bares its king
+                let lone_piece = count_bits(board.occupancy[opponent as usize]) == 2;
+                let can_bare_back = lone_piece && board.generate_legal_moves(to_move).iter().any(|mv| mv.is_capture());
+                (!can_bare_back).then_some(opponent)
+            }
+            (false, true) => Some(to_move),
+            _ => None,
+        }
+    }
+
+    /// How the game stands for the side to move, given the hashes of every earlier position oldest first,
+    /// or `None` while it goes on. Checkmate takes precedence over the move-count draws, and the automatic
+    /// draws over the claimable ones.
+    pub fn outcome(board: &Board, history: &[u64]) -> Option<GameOutcome> {
+        let to_move = board.side_to_move;
+        if board.generate_legal_moves(to_move).is_empty() {
+            return Some(if Self::is_in_check(board, to_move) {
+                GameOutcome::Checkmate { winner: to_move.opponent() }
+            } else if board.variant().stalemate_loses() {
+                GameOutcome::StalemateWin { winner: to_move.opponent() }
+            } else {
+                GameOutcome::Stalemate
+            });
+        }
+
+        let repetitions = Self::repetitions(board, history);
+        if Self::has_insufficient_material(board) {
+            Some(GameOutcome::InsufficientMaterial)
+        } else if let Some(winner) = Self::bare_king_winner(board) {
+            Some(GameOutcome::BareKing { winner })
+        } else if repetitions >= 5 {
+            Some(GameOutcome::FivefoldRepetition)
+        } else if board.halfmove_clock >= 150 {
+            Some(GameOutcome::SeventyFiveMoveRule)
+        } else if repetitions >= 3 {
+            Some(GameOutcome::ThreefoldRepetition)
+        } else if board.halfmove_clock >= 100 {
+            Some(GameOutcome::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
 }
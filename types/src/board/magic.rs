@@ -0,0 +1,141 @@
+use std::sync::OnceLock;
+use crate::board::attacks::{bishop_attacks_slow, rook_attacks_slow};
+use crate::{count_bits, Bitboard};
+
+const RANK_EDGES: Bitboard = 0xFF00_0000_0000_00FF;
+const FILE_EDGES: Bitboard = 0x8181_8181_8181_8181;
+
+/// Per-rank PRNG seeds that find a full set of magics quickly (the same ones Stockfish uses).
+const SEEDS: [u64; 8] = [728, 10316, 55013, 32803, 12281, 15100, 16645, 255];
+
+/// Everything needed to turn an occupancy into an index into the shared attack table for one square.
+#[derive(Clone, Copy, Default)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    #[inline(always)]
+    fn index(&self, occupancy: Bitboard) -> usize {
+        self.offset + ((occupancy & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct MagicTables {
+    bishops: [Magic; 64],
+    rooks: [Magic; 64],
+    attacks: Vec<Bitboard>,
+}
+
+/// Built on first use and shared by every `Board`, so boards themselves stay small and `Copy`.
+static TABLES: OnceLock<MagicTables> = OnceLock::new();
+
+#[inline(always)]
+fn tables() -> &'static MagicTables {
+    TABLES.get_or_init(MagicTables::generate)
+}
+
+#[inline(always)]
+pub(crate) fn bishop_attacks(square: usize, occupancy: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.bishops[square].index(occupancy)]
+}
+
+#[inline(always)]
+pub(crate) fn rook_attacks(square: usize, occupancy: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.rooks[square].index(occupancy)]
+}
+
+/// xorshift64* generator, deterministic so the same magics come out on every run.
+struct Prng(u64);
+
+impl Prng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(2_685_821_657_736_338_717)
+    }
+
+    /// Magics with few set bits are found much faster.
+    fn sparse(&mut self) -> u64 {
+        self.next() & self.next() & self.next()
+    }
+}
+
+impl MagicTables {
+    fn generate() -> Self {
+        let mut attacks = Vec::with_capacity(5_248 + 102_400);
+        let bishops = Self::generate_slider(bishop_attacks_slow, &mut attacks);
+        let rooks = Self::generate_slider(rook_attacks_slow, &mut attacks);
+
+        MagicTables { bishops, rooks, attacks }
+    }
+
+    /// Finds a magic for every square and appends that square's attack table to `attacks`.
+    fn generate_slider(slow_attacks: fn(usize, Bitboard) -> Bitboard, attacks: &mut Vec<Bitboard>) -> [Magic; 64] {
+        let mut magics = [Magic::default(); 64];
+        let mut occupancies = Vec::with_capacity(4096);
+        let mut reference = Vec::with_capacity(4096);
+
+        for (square, entry) in magics.iter_mut().enumerate() {
+            // Edge squares never block anything beyond themselves, unless the slider stands on that edge
+            let rank_edges = RANK_EDGES & !(0xFF << (square / 8 * 8));
+            let file_edges = FILE_EDGES & !(0x0101_0101_0101_0101 << (square % 8));
+            let mask = slow_attacks(square, 0) & !(rank_edges | file_edges);
+            let bits = count_bits(mask);
+
+            // Enumerate every subset of the mask (Carry-Rippler) along with its true attack set
+            occupancies.clear();
+            reference.clear();
+            let mut subset: Bitboard = 0;
+            loop {
+                occupancies.push(subset);
+                reference.push(slow_attacks(square, subset));
+                subset = subset.wrapping_sub(mask) & mask;
+                if subset == 0 {
+                    break;
+                }
+            }
+
+            let size = occupancies.len();
+            let offset = attacks.len();
+            attacks.resize(offset + size, 0);
+
+            // Attempt number each slot was last written in, so the table never needs clearing between attempts
+            let mut epoch = vec![0u32; size];
+            let mut prng = Prng(SEEDS[square / 8]);
+            let mut attempt = 0;
+
+            *entry = loop {
+                let magic = prng.sparse();
+                if count_bits(mask.wrapping_mul(magic) >> 56) < 6 {
+                    continue;
+                }
+
+                attempt += 1;
+                let candidate = Magic { mask, magic, shift: 64 - bits, offset };
+                let collision_free = occupancies.iter().zip(&reference).all(|(&occupancy, &expected)| {
+                    let index = candidate.index(occupancy) - offset;
+                    if epoch[index] < attempt {
+                        epoch[index] = attempt;
+                        attacks[offset + index] = expected;
+                        true
+                    } else {
+                        attacks[offset + index] == expected // Constructive collision
+                    }
+                });
+
+                if collision_free {
+                    break candidate;
+                }
+            };
+        }
+
+        magics
+    }
+}
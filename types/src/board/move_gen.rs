@@ -1,70 +1,442 @@
-use crate::board::Board;
-use crate::{clear_bit, get_lsb, set_bit, Bitboard, Color, Piece, Square};
+use crate::board::{Board, Undo, Variant};
+use crate::board::castling_right;
+use crate::board::attacks::{alfil_attacks, between, bishop_attacks, ferz_attacks, king_attacks, knight_attacks, line, pawn_attacks, rook_attacks};
+use crate::{clear_bit, count_bits, get_lsb, is_bit_set, set_bit, Bitboard, ChessMove, Color, MoveFlag, Piece, Square, Zobrist, FULL_BOARD};
 
 impl Board{
-    #[inline(always)]
-    pub fn make_move(&mut self, from: Square, to: Square, piece: Piece, color: Color) {
-        let piece_index = self.get_piece_index(piece, color);
-        clear_bit(&mut self.pieces[piece_index], from.to_index());
-        set_bit(&mut self.pieces[piece_index], to.to_index());
-
-        // Handle en passant, castling, and other special moves
-        self.en_passant = None; // Reset en passant target square
-        if piece == Piece::Pawn && (to.to_index() as i32 - from.to_index() as i32).abs() == 16 {
-            self.en_passant = Some(Square::from_index((from.to_index() + to.to_index()) / 2));
+    /// Plays a move for the side that owns the moving piece and returns what `unmake_move` needs to take it back.
+    pub fn make_move(&mut self, mv: ChessMove) -> Undo {
+        let (from_square, to_square) = (mv.from(), mv.to());
+        let mut undo = Undo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            side_to_move: self.side_to_move,
+            hash: self.hash,
+            checkers: self.checkers,
+        };
+
+        let Some((piece, color)) = self.get_piece_at_square(from_square) else {
+            return undo; // Nothing to move; the record still restores the position unchanged
+        };
+
+        self.en_passant = None;
+
+        match mv.flag() {
+            MoveFlag::KingCastle => self.castle(from_square, color, true),
+            MoveFlag::QueenCastle => self.castle(from_square, color, false),
+            MoveFlag::EnPassant => {
+                self.capture_piece(Self::en_passant_capture_square(to_square, color));
+                undo.captured = Some(Piece::Pawn);
+                self.move_piece(from_square, to_square, Piece::Pawn, color);
+            }
+            _ => {
+                if mv.is_capture() {
+                    undo.captured = self.get_piece_at_square(to_square).map(|(captured, _)| captured);
+                    self.capture_piece(to_square);
+                }
+                self.move_piece(from_square, to_square, piece, color);
+
+                if let Some(promoted_piece) = mv.promotion() {
+                    self.promote_pawn(to_square, promoted_piece, color);
+                }
+
+                if mv.is_double_pawn_push() {
+                    // The square is only recorded, and hashed, when an enemy pawn can take on it: otherwise the
+                    // position is the same as one reached without the push, and repetitions must match it
+                    let skipped = (from_square.to_index() + to_square.to_index()) / 2;
+                    let enemy_pawns = self.pieces[self.get_piece_index(Piece::Pawn, color.opponent())];
+                    if pawn_attacks(color, skipped) & enemy_pawns != 0 {
+                        self.en_passant = Some(Square::from_index(skipped));
+                    }
+                }
+            }
         }
 
-        self.update_occupancy();
-        self.side_to_move = self.side_to_move.opponent();
-        self.halfmove_clock += 1;
+        // Update castling rights if a king or rook moved or a rook was captured
+        self.update_castling_rights(piece, color, from_square, to_square);
+
+        if piece == Piece::Pawn || mv.is_capture() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
         if color == Color::Black {
             self.fullmove_number += 1;
         }
+
+        // Switch sides
+        self.side_to_move = color.opponent();
+
+        // Pieces were hashed as they moved; fold in whatever else changed
+        let zobrist = Zobrist::global();
+        self.hash ^= zobrist.castling_keys[undo.castling_rights as usize] ^ zobrist.castling_keys[self.castling_rights as usize];
+        self.hash ^= zobrist.en_passant_key(undo.en_passant) ^ zobrist.en_passant_key(self.en_passant);
+        if self.side_to_move != undo.side_to_move {
+            self.hash ^= zobrist.side_to_move_key;
+        }
+        debug_assert_eq!(self.hash, self.compute_zobrist_hash(zobrist), "incremental hash drifted after {}", mv);
+        self.checkers = self.find_checkers();
+
+        undo
     }
 
-    #[inline(always)]
-    pub fn unmake_move(&mut self, from: Square, to: Square, piece: Piece, color: Color) {
-        let piece_index = self.get_piece_index(piece, color);
-        clear_bit(&mut self.pieces[piece_index], to.to_index());
-        set_bit(&mut self.pieces[piece_index], from.to_index());
+    /// Takes back a move played by `make_move`, restoring the position exactly from its undo record.
+    pub fn unmake_move(&mut self, mv: ChessMove, undo: Undo) {
+        let (from_square, to_square) = (mv.from(), mv.to());
+
+        if let Some((piece, color)) = self.get_piece_at_square(to_square) {
+            match mv.flag() {
+                MoveFlag::KingCastle => self.castle(from_square, color, true),
+                MoveFlag::QueenCastle => self.castle(from_square, color, false),
+                _ => {
+                    // A promoted piece goes back to being a pawn
+                    let moved_piece = if mv.is_promotion() { Piece::Pawn } else { piece };
+                    let piece_index = self.get_piece_index(piece, color);
+                    clear_bit(&mut self.pieces[piece_index], to_square.to_index());
+                    let moved_index = self.get_piece_index(moved_piece, color);
+                    set_bit(&mut self.pieces[moved_index], from_square.to_index());
+
+                    if let Some(captured) = undo.captured {
+                        let capture_square = if mv.is_en_passant() {
+                            Self::en_passant_capture_square(to_square, color)
+                        } else {
+                            to_square
+                        };
+                        let captured_index = self.get_piece_index(captured, color.opponent());
+                        set_bit(&mut self.pieces[captured_index], capture_square.to_index());
+                    }
 
-        self.update_occupancy();
-        self.side_to_move = self.side_to_move.opponent();
-        if color == Color::Black {
-            self.fullmove_number -= 1;
+                    self.update_occupancy();
+                }
+            }
         }
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.side_to_move = undo.side_to_move;
+        self.hash = undo.hash;
+        self.checkers = undo.checkers;
     }
 
+    /// The square of the pawn taken by an en passant capture landing on `to_square`.
     #[inline(always)]
-    pub fn do_null_move(&mut self) {
-        self.side_to_move = self.side_to_move.opponent();
+    fn en_passant_capture_square(to_square: Square, color: Color) -> Square {
+        match color {
+            Color::White => Square::from_index(to_square.to_index() - 8),
+            Color::Black => Square::from_index(to_square.to_index() + 8),
+        }
     }
 
+    /// Passes the turn without moving, clearing any en passant square; `undo_null_move` reverses it.
     #[inline(always)]
-    pub fn undo_null_move(&mut self) {
+    pub fn do_null_move(&mut self) -> Undo {
+        let undo = Undo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            side_to_move: self.side_to_move,
+            hash: self.hash,
+            checkers: self.checkers,
+        };
+
+        let zobrist = Zobrist::global();
+        self.hash ^= zobrist.en_passant_key(self.en_passant) ^ zobrist.side_to_move_key;
+        self.en_passant = None;
         self.side_to_move = self.side_to_move.opponent();
+        self.checkers = self.find_checkers();
+
+        undo
     }
-    
+
     #[inline(always)]
-    pub fn generate_legal_moves(&self, color: Color) -> Vec<(Square, Square)> {
-        let mut moves = Vec::new();
-        for piece in 0..6 {
-            let piece_bb = self.pieces[self.get_piece_index(Piece::from_u8(piece as u8).expect("Invalid piece index"), color)];
-            let mut bb = piece_bb;
-            while bb != 0 {
-                let from_square = Square::from_index(get_lsb(bb));
+    pub fn undo_null_move(&mut self, undo: Undo) {
+        self.en_passant = undo.en_passant;
+        self.side_to_move = undo.side_to_move;
+        self.hash = undo.hash;
+        self.checkers = undo.checkers;
+    }
+    
+    /// Generates every legal move for the given color.
+    ///
+    /// Pinned pieces are restricted to the line through their king, and while in check only
+    /// king moves, captures of the checker and interpositions are produced.
+    pub fn generate_legal_moves(&self, color: Color) -> Vec<ChessMove> {
+        let mut moves = Vec::with_capacity(64);
+
+        let king_bitboard = self.pieces[self.get_piece_index(Piece::King, color)];
+        if king_bitboard == 0 {
+            return moves; // Not a playable position without a king
+        }
+        let king_square = get_lsb(king_bitboard);
+        let friendly = self.occupancy[color as usize];
+        let occupancy = self.occupancy();
+
+        // King moves are checked against the board with the king lifted, so it cannot hide behind itself
+        let mut king_targets = king_attacks(king_square) & !friendly;
+        while king_targets != 0 {
+            let to_index = get_lsb(king_targets);
+            if self.attackers_of(to_index, color.opponent(), occupancy ^ king_bitboard) == 0 {
+                moves.push(self.piece_move(Square::from_index(king_square), Square::from_index(to_index), color));
+            }
+            clear_bit(&mut king_targets, to_index);
+        }
+
+        let checkers = if color == self.side_to_move { self.checkers } else { self.attackers_of(king_square, color.opponent(), occupancy) };
+        if count_bits(checkers) > 1 {
+            return moves; // Double check: only the king may move
+        }
 
-                self.generate_move_from_square(color, &mut moves, piece, from_square);
+        // In single check every other move must capture the checker or, if it slides, block the line to it
+        let check_mask = if checkers == 0 {
+            self.generate_castling_moves(color, king_square, &mut moves);
+            FULL_BOARD
+        } else {
+            let (diagonal, straight) = self.sliders(color.opponent());
+            let blocks = if checkers & (diagonal | straight) != 0 { between(king_square, get_lsb(checkers)) } else { 0 };
+            checkers | blocks
+        };
+
+        let pinned = self.pinned_pieces(color, king_square);
+
+        for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+            let mut piece_bitboard = self.pieces[self.get_piece_index(piece, color)];
+            while piece_bitboard != 0 {
+                let from_index = get_lsb(piece_bitboard);
+                let from_square = Square::from_index(from_index);
+
+                let mut target_mask = check_mask;
+                if is_bit_set(pinned, from_index) {
+                    target_mask &= line(king_square, from_index);
+                }
 
-                // Clear the bit corresponding to the current piece's square
-                clear_bit(&mut bb, from_square.to_index());
+                if piece == Piece::Pawn {
+                    self.add_pawn_moves(from_square, color, target_mask, &mut moves);
+                    self.add_en_passant_moves(from_square, color, king_square, &mut moves);
+                } else {
+                    let targets = self.piece_attacks(piece, from_index, occupancy) & !friendly & target_mask;
+                    self.add_sliding_piece_moves(from_square, targets, color, &mut moves);
+                }
+
+                clear_bit(&mut piece_bitboard, from_index);
             }
         }
+
         moves
     }
 
+    /// Every piece of either colour that attacks the square, with sliders seeing through to `occupancy`.
+    /// Passing an occupancy other than the board's asks what would attack the square were pieces added or lifted.
     #[inline(always)]
-    fn generate_move_from_square(&self, color: Color, moves: &mut Vec<(Square, Square)>, piece: i32, from_square: Square) {
+    pub fn attackers_to(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        self.attackers_of(square.to_index(), Color::White, occupancy) | self.attackers_of(square.to_index(), Color::Black, occupancy)
+    }
+
+    /// Whether any piece of colour `by` attacks the square on the current board.
+    #[inline(always)]
+    pub fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+        self.attackers_of(square.to_index(), by, self.occupancy()) != 0
+    }
+
+    /// The pieces giving check to the side to move. Kept up to date as moves are made, so this costs nothing.
+    #[inline(always)]
+    pub fn checkers(&self) -> Bitboard {
+        self.checkers
+    }
+
+    /// Works out the checkers of the side to move from scratch, for `checkers` to hand out.
+    #[inline(always)]
+    pub(crate) fn find_checkers(&self) -> Bitboard {
+        let king = self.pieces[self.get_piece_index(Piece::King, self.side_to_move)];
+        if king == 0 {
+            return 0;
+        }
+        self.attackers_of(get_lsb(king), self.side_to_move.opponent(), self.occupancy())
+    }
+
+    /// Returns all pieces of the given color that attack the square, using the supplied occupancy for sliders.
+    #[inline(always)]
+    pub(crate) fn attackers_of(&self, square: usize, by: Color, occupancy: Bitboard) -> Bitboard {
+        let (diagonal, straight) = self.sliders(by);
+        let shatranj_leapers = match self.variant {
+            Variant::Standard => 0,
+            Variant::Shatranj => {
+                (ferz_attacks(square) & self.pieces[self.get_piece_index(Piece::Queen, by)])
+                    | (alfil_attacks(square) & self.pieces[self.get_piece_index(Piece::Bishop, by)])
+            }
+        };
+
+        (pawn_attacks(by.opponent(), square) & self.pieces[self.get_piece_index(Piece::Pawn, by)])
+            | (knight_attacks(square) & self.pieces[self.get_piece_index(Piece::Knight, by)])
+            | (king_attacks(square) & self.pieces[self.get_piece_index(Piece::King, by)])
+            | (bishop_attacks(square, occupancy) & diagonal)
+            | (rook_attacks(square, occupancy) & straight)
+            | shatranj_leapers
+    }
+
+    /// The pieces of the given color that slide along diagonals, and those that slide along ranks and files.
+    /// In Shatranj only the rook slides.
+    #[inline(always)]
+    fn sliders(&self, color: Color) -> (Bitboard, Bitboard) {
+        let rooks = self.pieces[self.get_piece_index(Piece::Rook, color)];
+        match self.variant {
+            Variant::Standard => {
+                let queens = self.pieces[self.get_piece_index(Piece::Queen, color)];
+                (self.pieces[self.get_piece_index(Piece::Bishop, color)] | queens, rooks | queens)
+            }
+            Variant::Shatranj => (0, rooks),
+        }
+    }
+
+    /// Returns the pieces of the given color that are pinned against their own king.
+    #[inline(always)]
+    fn pinned_pieces(&self, color: Color, king_square: usize) -> Bitboard {
+        let opponent = color.opponent();
+        let enemy = self.occupancy[opponent as usize];
+        let (diagonal, straight) = self.sliders(opponent);
+
+        // Enemy sliders that would see the king if our own pieces were transparent
+        let mut snipers = (rook_attacks(king_square, enemy) & straight) | (bishop_attacks(king_square, enemy) & diagonal);
+
+        let mut pinned = 0;
+        while snipers != 0 {
+            let sniper = get_lsb(snipers);
+            let blockers = between(king_square, sniper) & self.occupancy();
+            if count_bits(blockers) == 1 {
+                pinned |= blockers & self.occupancy[color as usize];
+            }
+            clear_bit(&mut snipers, sniper);
+        }
+
+        pinned
+    }
+
+    #[inline(always)]
+    fn piece_attacks(&self, piece: Piece, square: usize, occupancy: Bitboard) -> Bitboard {
+        match (piece, self.variant) {
+            (Piece::Bishop, Variant::Shatranj) => alfil_attacks(square),
+            (Piece::Queen, Variant::Shatranj) => ferz_attacks(square),
+            _ => self.standard_piece_attacks(piece, square, occupancy),
+        }
+    }
+
+    #[inline(always)]
+    fn standard_piece_attacks(&self, piece: Piece, square: usize, occupancy: Bitboard) -> Bitboard {
+        match piece {
+            Piece::Knight => knight_attacks(square),
+            Piece::Bishop => bishop_attacks(square, occupancy),
+            Piece::Rook => rook_attacks(square, occupancy),
+            Piece::Queen => bishop_attacks(square, occupancy) | rook_attacks(square, occupancy),
+            Piece::King => king_attacks(square),
+            Piece::Pawn => 0, // Pawns move and capture differently, see `add_pawn_moves`
+        }
+    }
+
+    /// Adds castling moves for the king on `king_square`; the caller guarantees it is not currently in check.
+    ///
+    /// The rules are Chess960's, which standard chess is a case of: every square the king or the rook
+    /// crosses or lands on must be empty but for the two of them, and no square the king crosses or lands
+    /// on may be attacked.
+    #[inline(always)]
+    fn generate_castling_moves(&self, color: Color, king_square: usize, moves: &mut Vec<ChessMove>) {
+        for (kingside, flag) in [(true, MoveFlag::KingCastle), (false, MoveFlag::QueenCastle)] {
+            if self.castling_rights & castling_right(color, kingside) == 0 {
+                continue;
+            }
+            let (king_from, king_to, rook_from, rook_to) = self.castling_squares(Square::from_index(king_square), color, kingside);
+            if self.get_piece_at_square(rook_from) != Some((Piece::Rook, color)) {
+                continue;
+            }
+
+            // With both castling pieces lifted, so a slider behind the rook is seen as well
+            let occupancy = self.occupancy() ^ (1u64 << king_from.to_index()) ^ (1u64 << rook_from.to_index());
+            let mut king_path = between(king_from.to_index(), king_to.to_index()) | (1u64 << king_to.to_index());
+            let rook_path = between(rook_from.to_index(), rook_to.to_index()) | (1u64 << rook_to.to_index());
+            if (king_path | rook_path) & occupancy != 0 {
+                continue;
+            }
+
+            let mut path_safe = true;
+            while king_path != 0 && path_safe {
+                let square = get_lsb(king_path);
+                path_safe = self.attackers_of(square, color.opponent(), occupancy) == 0;
+                clear_bit(&mut king_path, square);
+            }
+
+            if path_safe {
+                moves.push(ChessMove::new(king_from, king_to, flag));
+            }
+        }
+    }
+
+    /// Adds pawn pushes, double pushes and captures (with promotions) that land inside `target_mask`.
+    /// Shatranj pawns never push two squares.
+    #[inline(always)]
+    fn add_pawn_moves(&self, from_square: Square, color: Color, target_mask: Bitboard, moves: &mut Vec<ChessMove>) {
+        let from_index = from_square.to_index();
+        let (forward, starting_rank) = match color {
+            Color::White => (from_index + 8, 1),
+            Color::Black => (from_index.wrapping_sub(8), 6),
+        };
+
+        // One-square forward move
+        if forward < 64 && !self.is_occupied(Square::from_index(forward)) {
+            if is_bit_set(target_mask, forward) {
+                self.push_pawn_move(from_square, Square::from_index(forward), false, moves);
+            }
+
+            // Two-square forward move from starting position
+            let double_forward = if color == Color::White { forward + 8 } else { forward.wrapping_sub(8) };
+            if self.variant == Variant::Standard
+                && from_square.rank_usize() == starting_rank
+                && !self.is_occupied(Square::from_index(double_forward))
+                && is_bit_set(target_mask, double_forward)
+            {
+                moves.push(ChessMove::new(from_square, Square::from_index(double_forward), MoveFlag::DoublePawnPush));
+            }
+        }
+
+        // Captures
+        let mut captures = pawn_attacks(color, from_index) & self.occupancy[color.opponent() as usize] & target_mask;
+        while captures != 0 {
+            let capture_index = get_lsb(captures);
+            self.push_pawn_move(from_square, Square::from_index(capture_index), true, moves);
+            clear_bit(&mut captures, capture_index);
+        }
+    }
+
+    /// Adds an en passant capture for this pawn if it is available and does not expose the king.
+    #[inline(always)]
+    fn add_en_passant_moves(&self, from_square: Square, color: Color, king_square: usize, moves: &mut Vec<ChessMove>) {
+        let Some(ep_square) = self.en_passant else {
+            return;
+        };
+        if color != self.side_to_move || !is_bit_set(pawn_attacks(color, from_square.to_index()), ep_square.to_index()) {
+            return;
+        }
+
+        // The captured pawn stands behind the target square
+        let captured_index = if color == Color::White { ep_square.to_index() - 8 } else { ep_square.to_index() + 8 };
+        let captured = 1u64 << captured_index;
+        if self.pieces[self.get_piece_index(Piece::Pawn, color.opponent())] & captured == 0 {
+            return;
+        }
+
+        // Two pawns leave the rank at once, so replay the occupancy and look for any remaining attacker
+        let occupancy_after = (self.occupancy() ^ (1u64 << from_square.to_index()) ^ captured) | (1u64 << ep_square.to_index());
+        if self.attackers_of(king_square, color.opponent(), occupancy_after) & !captured == 0 {
+            moves.push(ChessMove::new(from_square, ep_square, MoveFlag::EnPassant));
+        }
+    }
+
+    #[inline(always)]
+    fn generate_move_from_square(&self, color: Color, moves: &mut Vec<ChessMove>, piece: i32, from_square: Square) {
         // Generate moves for this piece from this square
         match Piece::from_u8(piece as u8).expect("Invalid piece index") {
             Piece::Pawn => self.generate_pawn_moves(from_square, color, moves),
@@ -78,7 +450,7 @@ impl Board{
 
     /// Generates all pseudo-legal moves for a specific piece from a given square.
     #[inline(always)]
-    pub fn generate_piece_moves(&self, piece: Piece, from_square: Square, color: Color) -> Vec<(Square, Square)> {
+    pub fn generate_piece_moves(&self, piece: Piece, from_square: Square, color: Color) -> Vec<ChessMove> {
         let mut moves = Vec::new();
 
         self.generate_move_from_square(color, &mut moves, piece as i32, from_square);
@@ -87,224 +459,98 @@ impl Board{
     }
 
     #[inline(always)]
-    pub fn generate_pawn_moves(&self, from_square: Square, color: Color, moves: &mut Vec<(Square, Square)>) {
-        let from_index = from_square.to_index();
-        let direction = if color == Color::White { 8 } else { -8 };
-    
-        // One-square forward move
-        let target_index = (from_index as isize + direction) as usize;
-        if target_index < 64 {
-            let target_square = Square::from_index(target_index);
-            if !self.is_occupied(target_square) {
-                moves.push((from_square, target_square));
-    
-                // Two-square forward move from starting position
-                // println!("Color is: {:?}", color);
-                // println!("Generating moves for {:?} during {:?}", color, self.side_to_move);
-
-                let starting_rank = if color == Color::White { 1 } else { 6 };
-                if from_square.rank_usize() == starting_rank {
-                    let double_target_index = (from_index as isize + 2 * direction) as usize;
-                    if double_target_index < 64 {
-                        let double_target_square = Square::from_index(double_target_index);
-                        if !self.is_occupied(double_target_square) {
-                            moves.push((from_square, double_target_square));
-                        }
-                    }
-                }
-            }
-        }
-    
-        // Captures
-        for &offset in &[-1, 1] {
-            let capture_index = (from_index as isize + direction + offset) as usize;
-            if capture_index < 64 {
-                let capture_square = Square::from_index(capture_index);
-                if self.is_occupied_by_opponent(capture_square, color) {
-                    moves.push((from_square, capture_square));
-                }
+    pub fn generate_pawn_moves(&self, from_square: Square, color: Color, moves: &mut Vec<ChessMove>) {
+        self.add_pawn_moves(from_square, color, FULL_BOARD, moves);
+    }
+
+    /// Pushes a pawn move, expanding it into the variant's promotion choices when it reaches the last rank.
+    #[inline(always)]
+    fn push_pawn_move(&self, from_square: Square, to_square: Square, capture: bool, moves: &mut Vec<ChessMove>) {
+        let last_rank = to_square.rank_usize() == 0 || to_square.rank_usize() == 7;
+        if last_rank {
+            for &piece in self.variant.promotion_pieces() {
+                moves.push(ChessMove::new(from_square, to_square, MoveFlag::promotion(piece, capture)));
             }
+        } else {
+            let flag = if capture { MoveFlag::Capture } else { MoveFlag::Quiet };
+            moves.push(ChessMove::new(from_square, to_square, flag));
         }
     }
 
+    /// Builds a non-pawn move to the given square, flagging it as a capture when an enemy piece stands there.
+    #[inline(always)]
+    fn piece_move(&self, from_square: Square, to_square: Square, color: Color) -> ChessMove {
+        let flag = if self.is_occupied_by_opponent(to_square, color) { MoveFlag::Capture } else { MoveFlag::Quiet };
+        ChessMove::new(from_square, to_square, flag)
+    }
 
     #[inline(always)]
-    pub fn generate_knight_moves(&self, from_square: Square, color: Color, moves: &mut Vec<(Square, Square)>) {
-        let from_index = from_square.to_index();
-        let knight_moves = [15, 17, 10, 6, -15, -17, -10, -6];
-    
-        for &offset in &knight_moves {
-            if let Some(to_index) = from_index.checked_add(offset as usize) {
-                if to_index < 64 {
-                    let to_square = Square::from_index(to_index);
-                    if !self.is_occupied_by_friendly(to_square, color) {
-                        moves.push((from_square, to_square));
-                    }
-                }
-            }
-        }
+    pub fn generate_knight_moves(&self, from_square: Square, color: Color, moves: &mut Vec<ChessMove>) {
+        let knight_moves = knight_attacks(from_square.to_index());
+        self.add_sliding_piece_moves(from_square, knight_moves, color, moves);
     }
 
     #[inline(always)]
-    pub fn generate_bishop_moves(&self, from_square: Square, color: Color, moves: &mut Vec<(Square, Square)>) {
-        let bishop_moves = self.get_bishop_attacks(from_square.to_index(), self.occupancy());
+    pub fn generate_bishop_moves(&self, from_square: Square, color: Color, moves: &mut Vec<ChessMove>) {
+        let bishop_moves = self.piece_attacks(Piece::Bishop, from_square.to_index(), self.occupancy());
         self.add_sliding_piece_moves(from_square, bishop_moves, color, moves);
     }
 
     #[inline(always)]
-    pub fn generate_rook_moves(&self, from_square: Square, color: Color, moves: &mut Vec<(Square, Square)>) {
-        let rook_moves = self.get_rook_attacks(from_square.to_index(), self.occupancy());
+    pub fn generate_rook_moves(&self, from_square: Square, color: Color, moves: &mut Vec<ChessMove>) {
+        let rook_moves = rook_attacks(from_square.to_index(), self.occupancy());
         self.add_sliding_piece_moves(from_square, rook_moves, color, moves);
     }
 
     #[inline(always)]
-    pub fn generate_queen_moves(&self, from_square: Square, color: Color, moves: &mut Vec<(Square, Square)>) {
-        let queen_moves = self.get_bishop_attacks(from_square.to_index(), self.occupancy())
-                         | self.get_rook_attacks(from_square.to_index(), self.occupancy());
+    pub fn generate_queen_moves(&self, from_square: Square, color: Color, moves: &mut Vec<ChessMove>) {
+        let queen_moves = self.piece_attacks(Piece::Queen, from_square.to_index(), self.occupancy());
         self.add_sliding_piece_moves(from_square, queen_moves, color, moves);
     }
 
     #[inline(always)]
-    pub fn generate_king_moves(&self, from_square: Square, color: Color, moves: &mut Vec<(Square, Square)>) {
-        let from_index = from_square.to_index();
-        let king_moves = [1, -1, 8, -8, 9, -9, 7, -7];
-
-        for &offset in &king_moves {
-            let to_index = from_index as isize + offset;
-
-            // Ensure the index is within bounds and that it doesn't wrap around rows
-            if (0..64).contains(&to_index) { // Same as: to_index >= 0 && to_index < 64
-                let to_square = Square::from_index(to_index as usize);
-
-                // Check for row wrapping: Ensure that moves don't wrap horizontally across the board
-                let from_file = from_square.file();
-                let to_file = to_square.file();
-                if (offset.abs() == 1 || offset.abs() == 9 || offset.abs() == 7) && from_file != to_file {
-                    continue; // Skip move if it wraps around the edge of the board
-                }
-
-                if !self.is_occupied_by_friendly(to_square, color) {
-                    moves.push((from_square, to_square));
-                }
-            }
-        }
+    pub fn generate_king_moves(&self, from_square: Square, color: Color, moves: &mut Vec<ChessMove>) {
+        let king_moves = king_attacks(from_square.to_index());
+        self.add_sliding_piece_moves(from_square, king_moves, color, moves);
     }
 
     #[inline(always)]
-    pub fn add_sliding_piece_moves(&self, from_square: Square, attacks: Bitboard, color: Color, moves: &mut Vec<(Square, Square)>) {
+    pub fn add_sliding_piece_moves(&self, from_square: Square, attacks: Bitboard, color: Color, moves: &mut Vec<ChessMove>) {
         let mut attack_bitboard = attacks;
         while attack_bitboard != 0 {
             let to_square_index = get_lsb(attack_bitboard);
             let to_square = Square::from_index(to_square_index);
             if !self.is_occupied_by_friendly(to_square, color) {
-                moves.push((from_square, to_square));
+                moves.push(self.piece_move(from_square, to_square, color));
             }
             clear_bit(&mut attack_bitboard, to_square_index);
         }
     }
     
+    /// Plays a move on the board, handling captures, castling, en passant and promotions from the move flags.
     #[inline(always)]
-    pub fn apply_move(&mut self, mv: (Square, Square), color: Color) {
-        let (from_square, to_square) = mv;
-
-        // Get the piece at the starting square
-        if let Some((piece, _)) = self.get_piece_at_square(from_square) {
-            // Handle special cases: Castling, En Passant, Promotion
-           if piece == Piece::King {
-                if from_square == Square::E1 && color == Color::White {
-                    if to_square == Square::G1 {
-                        self.castle_kingside();
-                        return;
-                    } else if to_square == Square::C1 {
-                        self.castle_queenside();
-                        return;
-                    }
-                } else if from_square == Square::E8 && color == Color::Black {
-                    if to_square == Square::G8 {
-                        self.castle_kingside();
-                        return;
-                    } else if to_square == Square::C8 {
-                        self.castle_queenside();
-                        return;
-                    }
-                }
-           }
-
-           if piece == Piece::Pawn {
-                // Handle en passant
-                if let Some(ep_square) = self.en_passant {
-                    if to_square == ep_square {
-                        let capture_index = (from_square.to_index() as isize + if color == Color::White { -8 } else { 8 }) as usize;
-                        if capture_index < 64 {
-                            let capture_square = Square::from_index(capture_index);
-                            self.capture_piece(capture_square);
-                        }
-                    }
-                }
-            
-                // Handle promotion
-                if (color == Color::White && to_square.rank_usize() == 7) || (color == Color::Black && to_square.rank_usize() == 0) {
-                    self.promote_pawn(to_square, Piece::Queen, color);
-                    self.side_to_move = color.opponent();
-                    return;
-                }
-            }
-
-            // Regular move: Update piece positions and handle capture
-            if self.is_occupied(to_square) {
-                self.capture_piece(to_square);
-            }
-            self.make_move(from_square, to_square, piece, color);
-
-            // Update en passant square if applicable
-            if piece == Piece::Pawn && (to_square.to_index() as isize - from_square.to_index() as isize).abs() == 16 {
-                self.en_passant = Some(Square::from_index((from_square.to_index() + to_square.to_index()) / 2));
-            } else {
-                self.en_passant = None;
-            }
-
-            // Update castling rights if necessary
-            self.update_castling_rights(from_square, to_square, piece, color);
-
-            // Switch sides
-            self.side_to_move = color.opponent();
-        }
+    pub fn apply_move(&mut self, mv: ChessMove, color: Color) {
+        debug_assert!(
+            self.get_piece_at_square(mv.from()).is_none_or(|(_, piece_color)| piece_color == color),
+            "apply_move called with the wrong color for {}", mv
+        );
+
+        // Applied moves are permanent, so the undo record is dropped
+        self.make_move(mv);
     }
 
     #[inline(always)]
-    fn update_castling_rights(&mut self, from_square: Square, to_square: Square, piece: Piece, color: Color) {
-        // Update castling rights if a rook or king moves
+    fn update_castling_rights(&mut self, piece: Piece, color: Color, from_square: Square, to_square: Square) {
+        // A king move loses both of its side's rights
         if piece == Piece::King {
-            if color == Color::White {
-                self.castling_rights &= !0b11; // White king moves, lose both castling rights
-            } else {
-                self.castling_rights &= !0b1100; // Black king moves, lose both castling rights
-            }
-        } else if piece == Piece::Rook {
-            if color == Color::White {
-                if from_square == Square::A1 {
-                    self.castling_rights &= !0b01; // Lose White queenside castling right
-                } else if from_square == Square::H1 {
-                    self.castling_rights &= !0b10; // Lose White kingside castling right
-                }
-            } else if from_square == Square::A8 {
-                self.castling_rights &= !0b0100; // Lose Black queenside castling right
-            } else if from_square == Square::H8 {
-                self.castling_rights &= !0b1000; // Lose Black kingside castling right
-            }
+            self.castling_rights &= !(castling_right(color, true) | castling_right(color, false));
         }
 
-        // Handle rook capture that impacts castling rights
-        if self.is_occupied(to_square) && self.get_piece_at_square(to_square).unwrap().0 == Piece::Rook {
-            if to_square == Square::A1 {
-                self.castling_rights &= !0b01; // Capturing White queenside rook
-            } else if to_square == Square::H1 {
-                self.castling_rights &= !0b10; // Capturing White kingside rook
-            } else if to_square == Square::A8 {
-                self.castling_rights &= !0b0100; // Capturing Black queenside rook
-            } else if to_square == Square::H8 {
-                self.castling_rights &= !0b1000; // Capturing Black kingside rook
+        // A move leaving or landing on a castling rook's square loses that right
+        for (index, &rook_square) in self.castling_rooks.iter().enumerate() {
+            if rook_square == from_square || rook_square == to_square {
+                self.castling_rights &= !(1 << index);
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,86 @@
+use std::str::FromStr;
+use crate::board::Board;
+use crate::{ChessMove, MoveFlag, Piece, Square};
+
+impl Board {
+    /// Builds a `ChessMove` from its squares, inferring the capture, castling,
+    /// en passant and double-push flags from the current position.
+    ///
+    /// A king taking its own rook is a castle with that rook, as Chess960 and Polyglot write it. Outside
+    /// Chess960 a king moving two files is a castle as well.
+    pub fn move_from_squares(&self, from_square: Square, to_square: Square, promotion: Option<Piece>) -> ChessMove {
+        let moved = self.get_piece_at_square(from_square);
+        let piece = moved.map(|(piece, _)| piece);
+        let file_distance = (from_square.file_usize() as isize - to_square.file_usize() as isize).abs();
+        let rank_distance = (from_square.rank_usize() as isize - to_square.rank_usize() as isize).abs();
+
+        if let Some((Piece::King, color)) = moved {
+            if self.get_piece_at_square(to_square) == Some((Piece::Rook, color)) {
+                let kingside = to_square.file_usize() > from_square.file_usize();
+                let (_, king_to, _, _) = self.castling_squares(from_square, color, kingside);
+                let flag = if kingside { MoveFlag::KingCastle } else { MoveFlag::QueenCastle };
+                return ChessMove::new(from_square, king_to, flag);
+            }
+        }
+
+        let capture = self.is_occupied(to_square);
+        let flag = match piece {
+            Some(Piece::King) if file_distance == 2 && rank_distance == 0 && !self.chess960 => {
+                if to_square.file_usize() > from_square.file_usize() {
+                    MoveFlag::KingCastle
+                } else {
+                    MoveFlag::QueenCastle
+                }
+            }
+            Some(Piece::Pawn) if promotion.is_some() => {
+                MoveFlag::promotion(promotion.expect("Checked above"), capture)
+            }
+            Some(Piece::Pawn) if rank_distance == 2 => MoveFlag::DoublePawnPush,
+            Some(Piece::Pawn) if file_distance == 1 && !capture && self.is_en_passant_target(to_square) => {
+                MoveFlag::EnPassant
+            }
+            _ if capture => MoveFlag::Capture,
+            _ => MoveFlag::Quiet,
+        };
+
+        ChessMove::new(from_square, to_square, flag)
+    }
+
+    /// Parses a move in UCI long algebraic notation (e.g. `e2e4`, `e7e8q`) against this position.
+    /// Castles may be given as the king's move or as the king taking its own rook, except that
+    /// Chess960 only knows the second.
+    pub fn parse_uci_move(&self, input: &str) -> Option<ChessMove> {
+        let input = input.trim();
+        if input.len() != 4 && input.len() != 5 {
+            return None;
+        }
+
+        let from_square = Square::from_str(input.get(0..2)?).ok()?;
+        let to_square = Square::from_str(input.get(2..4)?).ok()?;
+        let promotion = match input.get(4..5) {
+            Some(promo) => Some(Piece::from_char(promo.chars().next()?).filter(|piece| {
+                matches!(piece, Piece::Knight | Piece::Bishop | Piece::Rook | Piece::Queen)
+            })?),
+            None => None,
+        };
+
+        Some(self.move_from_squares(from_square, to_square, promotion))
+    }
+
+    /// Decodes a Polyglot book move against this position.
+    ///
+    /// Polyglot encodes castling as the king capturing its own rook, which `move_from_squares` reads as a castle.
+    pub fn move_from_polyglot(&self, polyglot_move: u16) -> ChessMove {
+        let from_square = Square::from_index(((polyglot_move >> 6) & 0x3F) as usize);
+        let to_square = Square::from_index((polyglot_move & 0x3F) as usize);
+        let promotion = match (polyglot_move >> 12) & 0x7 {
+            1 => Some(Piece::Knight),
+            2 => Some(Piece::Bishop),
+            3 => Some(Piece::Rook),
+            4 => Some(Piece::Queen),
+            _ => None,
+        };
+
+        self.move_from_squares(from_square, to_square, promotion)
+    }
+}
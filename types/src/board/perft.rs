@@ -0,0 +1,38 @@
+use crate::board::Board;
+use crate::ChessMove;
+
+impl Board {
+    /// Counts the leaf nodes of the legal move tree to the given depth, from the side to move.
+    ///
+    /// Moves at the last ply are counted straight from the generator instead of being played.
+    pub fn perft(&self, depth: u32) -> u64 {
+        let moves = self.generate_legal_moves(self.side_to_move);
+
+        match depth {
+            0 => 1,
+            1 => moves.len() as u64,
+            _ => moves.into_iter().map(|mv| self.perft_after(mv, depth - 1)).sum(),
+        }
+    }
+
+    /// Runs perft and reports the node count below every root move, in generation order.
+    ///
+    /// Comparing this against another engine's output pinpoints the exact move where the counts diverge.
+    pub fn divide(&self, depth: u32) -> Vec<(ChessMove, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+
+        self.generate_legal_moves(self.side_to_move)
+            .into_iter()
+            .map(|mv| (mv, self.perft_after(mv, depth - 1)))
+            .collect()
+    }
+
+    #[inline(always)]
+    fn perft_after(&self, mv: ChessMove, depth: u32) -> u64 {
+        let mut child = *self;
+        child.apply_move(mv, self.side_to_move);
+        child.perft(depth)
+    }
+}
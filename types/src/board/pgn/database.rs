@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use crate::board::pgn::reader::Games;
+use crate::board::pgn::{Game, PgnError, RESULTS};
+
+/// A problem met while streaming a PGN database.
+#[derive(Debug)]
+pub enum DatabaseError {
+    /// Reading the file failed; the database yields nothing after this.
+    Io(io::Error),
+    /// The `number`th game of the file (counting from 1) could not be read and was skipped.
+    Game { number: usize, error: PgnError },
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Io(error) => write!(f, "{}", error),
+            DatabaseError::Game { number, error } => write!(f, "game {}: {}", number, error),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+/// Follows PGN text line by line, well enough to tell where one game's text ends.
+#[derive(Default)]
+struct LineScanner {
+    in_movetext: bool,
+    in_comment: bool,
+    variation_depth: usize,
+}
+
+impl LineScanner {
+    /// Takes in a line, returning true when it ends the game with a result outside any comment or variation.
+    fn scan(&mut self, line: &str) -> bool {
+        let trimmed = line.trim();
+        if !self.in_comment && (trimmed.is_empty() || trimmed.starts_with('%') || (!self.in_movetext && trimmed.starts_with('['))) {
+            return false;
+        }
+        self.in_movetext = true;
+
+        let mut last_token = "";
+        let mut token_start = None;
+        for (index, c) in line.char_indices() {
+            if self.in_comment {
+                self.in_comment = c != '}';
+                continue;
+            }
+            let ends_token = matches!(c, '{' | ';' | '(' | ')') || c.is_whitespace();
+            if ends_token {
+                if let Some(start) = token_start.take() {
+                    last_token = &line[start..index];
+                }
+            } else if token_start.is_none() {
+                token_start = Some(index);
+            }
+            match c {
+                '{' => self.in_comment = true,
+                ';' => break,
+                '(' => self.variation_depth += 1,
+                ')' => self.variation_depth = self.variation_depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        if let Some(start) = token_start {
+            last_token = &line[start..];
+        }
+
+        !self.in_comment && self.variation_depth == 0 && RESULTS.contains(&last_token)
+    }
+}
+
+/// Streams the games of a PGN file of any size, holding only one game's text at a time.
+///
+/// Games that cannot be read come out as errors and reading carries on with the next one. With
+/// `headers_only` the movetext is skipped without being parsed, and each game comes out with its tags
+/// and an empty main line, which makes filtering a large database on its tags cheap.
+pub struct PgnDatabase<R> {
+    reader: R,
+    headers_only: bool,
+    carried: Option<(String, usize)>, // The next game's first line, read while finding the end of the last game
+    lines_read: usize,
+    games_read: usize,
+    pending: VecDeque<Result<Game, DatabaseError>>, // Games of the last chunk of text not handed out yet
+    done: bool,
+}
+
+impl PgnDatabase<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(PgnDatabase::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> PgnDatabase<R> {
+    pub fn new(reader: R) -> Self {
+        PgnDatabase { reader, headers_only: false, carried: None, lines_read: 0, games_read: 0, pending: VecDeque::new(), done: false }
+    }
+
+    /// Reads only the tag pairs of each game.
+    pub fn headers_only(mut self, headers_only: bool) -> Self {
+        self.headers_only = headers_only;
+        self
+    }
+
+    /// Games handed out or skipped so far.
+    #[inline(always)]
+    pub fn games_read(&self) -> usize {
+        self.games_read
+    }
+
+    /// Reads the text of the next game, with the line it starts on, or `None` at the end of the file.
+    ///
+    /// Games normally start at their tags, but a file of games without tags is split after each result.
+    fn read_chunk(&mut self) -> io::Result<Option<(String, usize)>> {
+        let mut chunk = String::new();
+        let mut first_line = None;
+        let mut scanner = LineScanner::default();
+
+        loop {
+            let (line, number) = match self.carried.take() {
+                Some(carried) => carried,
+                None => {
+                    let mut line = String::new();
+                    if self.reader.read_line(&mut line)? == 0 {
+                        break;
+                    }
+                    self.lines_read += 1;
+                    (line, self.lines_read)
+                }
+            };
+
+            if scanner.in_movetext && !scanner.in_comment && line.trim_start().starts_with('[') {
+                self.carried = Some((line, number));
+                break;
+            }
+            if first_line.is_none() && line.trim().is_empty() {
+                continue;
+            }
+            first_line.get_or_insert(number);
+
+            let ends_game = scanner.scan(&line);
+            if !(self.headers_only && scanner.in_movetext) {
+                chunk.push_str(&line);
+            }
+            if ends_game {
+                break;
+            }
+        }
+
+        Ok(first_line.map(|line| (chunk, line)))
+    }
+}
+
+impl<R: BufRead> Iterator for PgnDatabase<R> {
+    type Item = Result<Game, DatabaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(game) = self.pending.pop_front() {
+                return Some(game);
+            }
+            if self.done {
+                return None;
+            }
+
+            let (chunk, first_line) = match self.read_chunk() {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => {
+                    self.done = true;
+                    continue;
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(DatabaseError::Io(error)));
+                }
+            };
+
+            let games = Games::new(&chunk).take(if self.headers_only { 1 } else { usize::MAX });
+            for game in games {
+                self.games_read += 1;
+                let number = self.games_read;
+                self.pending.push_back(game.map_err(|error| DatabaseError::Game { number, error: error.offset_lines(first_line - 1) }));
+            }
+        }
+    }
+}
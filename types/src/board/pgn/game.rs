@@ -0,0 +1,116 @@
+use crate::board::pgn::reader::Games;
+use crate::board::pgn::PgnError;
+use crate::{Board, ChessMove, GameOutcome, GameState};
+
+/// The tags the PGN standard requires in every exported game, in the order they are written.
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// A move of a game with its annotations and the alternatives given for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameMove {
+    pub mv: ChessMove,
+    /// Numeric annotation glyphs; `!` and `?` style suffixes are read as their glyphs 1 to 6.
+    pub nags: Vec<u8>,
+    /// The comment that follows the move.
+    pub comment: Option<String>,
+    /// Lines played instead of this move, from the same position.
+    pub variations: Vec<Line>,
+}
+
+impl GameMove {
+    pub fn new(mv: ChessMove) -> Self {
+        GameMove { mv, nags: Vec::new(), comment: None, variations: Vec::new() }
+    }
+}
+
+/// A sequence of moves: the main line of a game or one of its variations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line {
+    /// A comment given before the first move.
+    pub comment: Option<String>,
+    pub moves: Vec<GameMove>,
+}
+
+/// One game of a PGN file: its tag pairs and its move tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    /// Tag pairs in the order they were read.
+    pub headers: Vec<(String, String)>,
+    /// The position the game starts from, which the `FEN` tag describes when it is not the usual one.
+    /// A `Variant` tag naming Shatranj loads it under Shatranj rules.
+    pub start: Board,
+    pub mainline: Line,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game { headers: Vec::new(), start: Board::new(), mainline: Line::default() }
+    }
+}
+
+impl Game {
+    /// A game starting from `start`, with no tags yet.
+    pub fn from_position(start: Board) -> Self {
+        Game { start, ..Game::default() }
+    }
+
+    /// Reads the first game of PGN text.
+    pub fn parse(text: &str) -> Result<Game, PgnError> {
+        Games::new(text).next().unwrap_or(Err(PgnError::NoGame))
+    }
+
+    /// Reads every game of a PGN file in turn. A malformed game yields its error and reading carries on with the next one.
+    pub fn parse_all(text: &str) -> Games<'_> {
+        Games::new(text)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a tag, replacing its value if it is already present.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// The game result (`1-0`, `0-1`, `1/2-1/2` or `*`), from the tag or the movetext terminator.
+    pub fn result(&self) -> Option<&str> {
+        self.header("Result")
+    }
+
+    /// The moves of the main line.
+    pub fn moves(&self) -> impl Iterator<Item = ChessMove> + '_ {
+        self.mainline.moves.iter().map(|node| node.mv)
+    }
+
+    /// The position at the end of the main line.
+    pub fn end_board(&self) -> Board {
+        let mut board = self.start;
+        for mv in self.moves() {
+            board.make_move(mv);
+        }
+        board
+    }
+
+    /// How the main line ends by the rules, judged from every position along it, or `None` if play could go on.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        let mut board = self.start;
+        let mut history = Vec::new();
+        for mv in self.moves() {
+            history.push(board.hash());
+            board.make_move(mv);
+        }
+        GameState::outcome(&board, &history)
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use crate::board::{Board, FenError, SanError};
+
+mod database;
+mod game;
+mod reader;
+mod tokenizer;
+mod writer;
+
+pub use database::{DatabaseError, PgnDatabase};
+pub use game::{Game, GameMove, Line, SEVEN_TAG_ROSTER};
+pub use reader::Games;
+pub use tokenizer::{Token, Tokenizer};
+
+/// The movetext terminators, which double as the values of the `Result` tag.
+pub const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// Why a game could not be read. Lines count from 1 at the start of the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// A token that has no place where it appears, such as a stray `)` or a tag inside a variation.
+    UnexpectedToken { token: String, line: usize },
+    /// The text ends inside a string, comment, tag pair or variation.
+    Unterminated { what: &'static str, line: usize },
+    /// The `FEN` tag does not describe a position.
+    Fen { error: FenError, line: usize },
+    /// A move that cannot be played in its position.
+    Move { error: SanError, line: usize },
+    /// The text holds no game at all.
+    NoGame,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::UnexpectedToken { token, line } => write!(f, "line {}: unexpected '{}'", line, token),
+            PgnError::Unterminated { what, line } => write!(f, "line {}: unterminated {}", line, what),
+            PgnError::Fen { error, line } => write!(f, "line {}: bad FEN tag: {}", line, error),
+            PgnError::Move { error, line } => write!(f, "line {}: {}", line, error),
+            PgnError::NoGame => write!(f, "no game found"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl PgnError {
+    /// The same error with its line moved down by `lines`, for text read from partway into a file.
+    pub(crate) fn offset_lines(self, lines: usize) -> Self {
+        match self {
+            PgnError::UnexpectedToken { token, line } => PgnError::UnexpectedToken { token, line: line + lines },
+            PgnError::Unterminated { what, line } => PgnError::Unterminated { what, line: line + lines },
+            PgnError::Fen { error, line } => PgnError::Fen { error, line: line + lines },
+            PgnError::Move { error, line } => PgnError::Move { error, line: line + lines },
+            PgnError::NoGame => PgnError::NoGame,
+        }
+    }
+}
+
+impl Board{
+    /// The position at the end of the main line of a PGN game.
+    #[inline(always)]
+    pub fn from_pgn(pgn: &str) -> Result<Self, PgnError> {
+        Game::parse(pgn).map(|game| game.end_board())
+    }
+
+    /// Plays one SAN move for the side to move.
+    #[inline(always)]
+    pub fn apply_move_from_pgn(&mut self, mov: &str) -> Result<&Self, SanError> {
+        let chess_move = self.parse_san(mov)?;
+        self.apply_move(chess_move, self.side_to_move);
+        Ok(self)
+    }
+}
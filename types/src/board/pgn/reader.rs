@@ -0,0 +1,174 @@
+use crate::board::pgn::game::{Game, GameMove, Line};
+use crate::board::pgn::tokenizer::{Token, Tokenizer};
+use crate::board::pgn::{PgnError, RESULTS};
+use crate::Board;
+
+/// Reads the games of a PGN file one at a time, straight from the tokens.
+pub struct Games<'a> {
+    tokens: Tokenizer<'a>,
+    peeked: Option<Result<Token, PgnError>>,
+    in_movetext: bool, // Whether the current game's tag section is behind us
+}
+
+impl<'a> Games<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Games { tokens: Tokenizer::new(text), peeked: None, in_movetext: false }
+    }
+
+    fn peek(&mut self) -> Option<&Result<Token, PgnError>> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn advance(&mut self) -> Result<Option<Token>, PgnError> {
+        self.peeked.take().or_else(|| self.tokens.next()).transpose()
+    }
+
+    fn unexpected(&self, token: Option<Token>, inside: &'static str) -> PgnError {
+        let line = self.tokens.line();
+        let token = match token {
+            None => return PgnError::Unterminated { what: inside, line },
+            Some(Token::TagStart) => "[".to_string(),
+            Some(Token::TagEnd) => "]".to_string(),
+            Some(Token::String(value)) => format!("\"{}\"", value),
+            Some(Token::Symbol(symbol)) => symbol,
+            Some(Token::Period) => ".".to_string(),
+            Some(Token::Asterisk) => "*".to_string(),
+            Some(Token::VariationStart) => "(".to_string(),
+            Some(Token::VariationEnd) => ")".to_string(),
+            Some(Token::Nag(nag)) => format!("${}", nag),
+            Some(Token::Comment(text)) => format!("{{{}}}", text),
+        };
+        PgnError::UnexpectedToken { token, line }
+    }
+
+    fn parse_game(&mut self) -> Result<Game, PgnError> {
+        self.in_movetext = false;
+        let mut game = Game::default();
+
+        while let Some(Ok(Token::TagStart)) = self.peek() {
+            self.advance()?;
+            let name = match self.advance()? {
+                Some(Token::Symbol(name)) => name,
+                token => return Err(self.unexpected(token, "tag pair")),
+            };
+            let value = match self.advance()? {
+                Some(Token::String(value)) => value,
+                token => return Err(self.unexpected(token, "tag pair")),
+            };
+            match self.advance()? {
+                Some(Token::TagEnd) => game.headers.push((name, value)),
+                token => return Err(self.unexpected(token, "tag pair")),
+            }
+        }
+        self.in_movetext = true;
+
+        // Variants this crate does not play are read as standard chess
+        let variant = game.header("Variant").and_then(|name| name.parse().ok()).unwrap_or_default();
+        game.start = match game.header("FEN") {
+            Some(fen) => Board::from_fen_variant(fen, variant).map_err(|error| PgnError::Fen { error, line: self.tokens.line() })?,
+            None => Board::new_variant(variant),
+        };
+
+        let mut result = None;
+        game.mainline = self.parse_line(game.start, 0, &mut result)?;
+        if let Some(result) = result.filter(|_| game.result().is_none()) {
+            game.headers.push(("Result".to_string(), result));
+        }
+        Ok(game)
+    }
+
+    /// Reads moves from `start` up to the end of the variation, or of the game when `depth` is 0.
+    fn parse_line(&mut self, start: Board, depth: usize, result: &mut Option<String>) -> Result<Line, PgnError> {
+        let mut line = Line::default();
+        let mut board = start;
+        let mut before = start; // Where the last move was played from, and so where its variations branch off
+
+        loop {
+            if depth == 0 && matches!(self.peek(), None | Some(Ok(Token::TagStart))) {
+                return Ok(line); // The file, or the game, ends without a terminator
+            }
+
+            match self.advance()? {
+                Some(Token::Symbol(symbol)) if RESULTS.contains(&symbol.as_str()) && depth == 0 => {
+                    *result = Some(symbol);
+                    return Ok(line);
+                }
+                Some(Token::Asterisk) if depth == 0 => {
+                    *result = Some("*".to_string());
+                    return Ok(line);
+                }
+                Some(Token::Symbol(symbol)) if symbol.bytes().all(|c| c.is_ascii_digit()) => {} // Move number
+                Some(Token::Symbol(symbol)) if !RESULTS.contains(&symbol.as_str()) => {
+                    let mv = board.parse_san(&symbol).map_err(|error| PgnError::Move { error, line: self.tokens.line() })?;
+                    before = board;
+                    board.make_move(mv);
+                    line.moves.push(GameMove::new(mv));
+                }
+                Some(Token::Period) => {}
+                Some(Token::Nag(nag)) => {
+                    if let Some(last) = line.moves.last_mut() {
+                        last.nags.push(nag);
+                    }
+                }
+                Some(Token::Comment(text)) => {
+                    let comment = match line.moves.last_mut() {
+                        Some(last) => &mut last.comment,
+                        None => &mut line.comment,
+                    };
+                    match comment {
+                        Some(existing) => {
+                            existing.push(' ');
+                            existing.push_str(&text);
+                        }
+                        None => *comment = Some(text),
+                    }
+                }
+                Some(Token::VariationStart) if !line.moves.is_empty() => {
+                    let variation = self.parse_line(before, depth + 1, result)?;
+                    if let Some(last) = line.moves.last_mut() {
+                        last.variations.push(variation);
+                    }
+                }
+                Some(Token::VariationEnd) if depth > 0 => return Ok(line),
+                token => return Err(self.unexpected(token, "variation")),
+            }
+        }
+    }
+
+    /// Skips the rest of a game that failed to parse: up to its terminator, or to the next game's tags.
+    fn skip_game(&mut self) {
+        let mut after_tag_start = false;
+        loop {
+            let in_movetext = self.in_movetext;
+            match self.peek() {
+                None => return,
+                Some(Ok(Token::TagStart)) if in_movetext => return,
+                _ => {}
+            }
+            match self.advance() {
+                Ok(Some(Token::Asterisk)) => return,
+                Ok(Some(Token::Symbol(symbol))) if RESULTS.contains(&symbol.as_str()) => return,
+                Ok(Some(Token::TagStart)) => after_tag_start = true,
+                Ok(Some(Token::Symbol(_))) if after_tag_start => after_tag_start = false,
+                Ok(Some(Token::String(_) | Token::TagEnd)) | Err(_) => {}
+                Ok(_) => self.in_movetext = true,
+            }
+        }
+    }
+}
+
+impl Iterator for Games<'_> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.peek()?;
+        let game = self.parse_game();
+        if game.is_err() {
+            self.skip_game();
+        }
+        Some(game)
+    }
+}
@@ -0,0 +1,154 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+use crate::board::pgn::PgnError;
+
+/// One lexical token of PGN, as laid out in section 7 of the standard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// `[`, opening a tag pair.
+    TagStart,
+    /// `]`, closing a tag pair.
+    TagEnd,
+    /// A quoted string with its escapes resolved.
+    String(String),
+    /// Tag names, move numbers, SAN moves and the results `1-0`, `0-1` and `1/2-1/2`.
+    Symbol(String),
+    Period,
+    /// `*`, the result of a game still in progress.
+    Asterisk,
+    VariationStart,
+    VariationEnd,
+    /// A `$n` glyph, or the suffix annotation (`!`, `?`, `!!`, `??`, `!?`, `?!`) it stands for.
+    Nag(u8),
+    /// A `{...}` or `;` comment, trimmed.
+    Comment(String),
+}
+
+/// Splits PGN text into tokens lazily, so a file can be walked without collecting it first.
+///
+/// Lines escaped with a leading `%` are skipped, as is a byte order mark.
+pub struct Tokenizer<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    at_line_start: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        Tokenizer { text, chars: text.char_indices().peekable(), line: 1, at_line_start: true }
+    }
+
+    /// The line the tokenizer has reached, counting from 1.
+    #[inline(always)]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// Consumes characters while `keep` holds, returning the text from `start` up to the first one it rejects.
+    fn take_while(&mut self, start: usize, keep: impl Fn(char) -> bool) -> &'a str {
+        while self.chars.peek().is_some_and(|&(_, c)| keep(c)) {
+            self.bump();
+        }
+        let end = self.chars.peek().map_or(self.text.len(), |&(index, _)| index);
+        &self.text[start..end]
+    }
+
+    fn string(&mut self) -> Result<Token, PgnError> {
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(Token::String(value)),
+                Some('\\') => match self.bump() {
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+        Err(PgnError::Unterminated { what: "string", line: self.line })
+    }
+
+    fn comment(&mut self) -> Result<Token, PgnError> {
+        let line = self.line;
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some('}') => return Ok(Token::Comment(text.split_whitespace().collect::<Vec<_>>().join(" "))),
+                Some(c) => text.push(c),
+                None => return Err(PgnError::Unterminated { what: "comment", line }),
+            }
+        }
+    }
+
+    fn suffix_annotation(&mut self, start: usize) -> Result<Token, PgnError> {
+        let glyph = self.take_while(start, |c| c == '!' || c == '?');
+        let nag = match glyph {
+            "!" => 1,
+            "?" => 2,
+            "!!" => 3,
+            "??" => 4,
+            "!?" => 5,
+            "?!" => 6,
+            _ => return Err(PgnError::UnexpectedToken { token: glyph.to_string(), line: self.line }),
+        };
+        Ok(Token::Nag(nag))
+    }
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Result<Token, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let &(start, c) = self.chars.peek()?;
+            let at_line_start = std::mem::replace(&mut self.at_line_start, c == '\n');
+
+            if c.is_whitespace() {
+                self.bump();
+                continue;
+            }
+            if c == '%' && at_line_start {
+                self.take_while(start, |c| c != '\n');
+                continue;
+            }
+
+            self.bump();
+            let token = match c {
+                '[' => Ok(Token::TagStart),
+                ']' => Ok(Token::TagEnd),
+                '(' => Ok(Token::VariationStart),
+                ')' => Ok(Token::VariationEnd),
+                '.' => Ok(Token::Period),
+                '*' => Ok(Token::Asterisk),
+                '"' => self.string(),
+                '{' => self.comment(),
+                ';' => {
+                    let text = self.take_while(start + 1, |c| c != '\n');
+                    Ok(Token::Comment(text.trim().to_string()))
+                }
+                '$' => {
+                    let digits = self.take_while(start + 1, |c| c.is_ascii_digit());
+                    digits.parse().map(Token::Nag).map_err(|_| PgnError::UnexpectedToken { token: format!("${}", digits), line: self.line })
+                }
+                '!' | '?' => self.suffix_annotation(start),
+                c if c.is_ascii_alphanumeric() => {
+                    let symbol = self.take_while(start, |c| c.is_ascii_alphanumeric() || "_+#=:-/".contains(c));
+                    Ok(Token::Symbol(symbol.to_string()))
+                }
+                c => Err(PgnError::UnexpectedToken { token: c.to_string(), line: self.line }),
+            };
+            return Some(token);
+        }
+    }
+}
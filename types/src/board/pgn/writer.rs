@@ -0,0 +1,111 @@
+use crate::board::pgn::game::{Game, Line, SEVEN_TAG_ROSTER};
+use crate::{Board, Color, Variant};
+
+/// Export format keeps movetext lines under 80 characters.
+const LINE_WIDTH: usize = 79;
+
+impl Game {
+    /// Writes the game in PGN export format: the Seven Tag Roster first, then the other tags, then the
+    /// movetext in canonical SAN wrapped to 79 columns and closed by the result.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+
+        let standard_start = self.start == Board::new();
+        let mut headers: Vec<(&str, String)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&(name, default)| (name, self.header(name).unwrap_or(default).to_string()))
+            .collect();
+        for (name, value) in &self.headers {
+            let is_written = SEVEN_TAG_ROSTER.iter().any(|&(roster, _)| roster == name) || name == "SetUp" || name == "FEN";
+            if !is_written {
+                headers.push((name, value.clone()));
+            }
+        }
+        if self.start.variant() != Variant::Standard && self.header("Variant").is_none() {
+            headers.push(("Variant", self.start.variant().to_string()));
+        }
+        if !standard_start {
+            headers.push(("SetUp", "1".to_string()));
+            headers.push(("FEN", self.start.to_fen()));
+        }
+        for (name, value) in headers {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        pgn.push('\n');
+
+        let mut words = Vec::new();
+        write_line(&self.start, &self.mainline, &mut words);
+        words.push(self.result().unwrap_or("*").to_string());
+
+        let mut row = String::new();
+        for word in words {
+            if !row.is_empty() && row.len() + 1 + word.len() > LINE_WIDTH {
+                pgn.push_str(&row);
+                pgn.push('\n');
+                row.clear();
+            }
+            if !row.is_empty() {
+                row.push(' ');
+            }
+            row.push_str(&word);
+        }
+        pgn.push_str(&row);
+        pgn.push('\n');
+        pgn
+    }
+}
+
+/// Appends a brace comment word by word, so long comments wrap like the moves around them.
+fn write_comment(comment: &str, words: &mut Vec<String>) {
+    let text = comment.replace('}', ")");
+    let mut comment_words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+    if comment_words.is_empty() {
+        comment_words.push(String::new());
+    }
+    comment_words[0].insert(0, '{');
+    if let Some(last) = comment_words.last_mut() {
+        last.push('}');
+    }
+    words.extend(comment_words);
+}
+
+/// Appends the movetext of a line played from `start`, numbering White's moves and any Black move that
+/// follows a comment or a variation.
+fn write_line(start: &Board, line: &Line, words: &mut Vec<String>) {
+    if let Some(comment) = &line.comment {
+        write_comment(comment, words);
+    }
+
+    let mut board = *start;
+    let mut needs_number = true;
+    for node in &line.moves {
+        if board.side_to_move == Color::White {
+            words.push(format!("{}.", board.fullmove_number));
+        } else if needs_number {
+            words.push(format!("{}...", board.fullmove_number));
+        }
+        words.push(board.move_to_san(node.mv));
+        words.extend(node.nags.iter().map(|nag| format!("${}", nag)));
+        needs_number = false;
+
+        if let Some(comment) = &node.comment {
+            write_comment(comment, words);
+            needs_number = true;
+        }
+        for variation in &node.variations {
+            let mut variation_words = Vec::new();
+            write_line(&board, variation, &mut variation_words);
+            if variation_words.is_empty() {
+                variation_words.push(String::new());
+            }
+            variation_words[0].insert(0, '(');
+            if let Some(last) = variation_words.last_mut() {
+                last.push(')');
+            }
+            words.extend(variation_words);
+            needs_number = true;
+        }
+
+        board.make_move(node.mv);
+    }
+}
@@ -3,7 +3,7 @@ use crate::{clear_bit, get_lsb, Bitboard, Board, Color, Piece, Square};
 impl Board{
     pub fn starting_position() -> Option<Board> {
         // Initialize the board with the standard starting FEN string
-        Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+        Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").ok()
     }
     
     pub fn is_complex(&self) -> bool {
@@ -25,6 +25,22 @@ impl Board{
         complexity_score > COMPLEXITY_THRESHOLD
     }
     
+    #[inline(always)]
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    #[inline(always)]
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Half-moves played since the start of the game, derived from the fullmove number and side to move.
+    #[inline(always)]
+    pub fn ply(&self) -> u32 {
+        (self.fullmove_number.max(1) - 1) * 2 + if self.side_to_move == Color::Black { 1 } else { 0 }
+    }
+
     /// Returns the square where the king of the given color is located.
     #[inline(always)]
     pub fn king_square(&self, color: Color) -> Square {
@@ -0,0 +1,159 @@
+use std::fmt;
+use crate::board::Board;
+use crate::{ChessMove, GameState, MoveFlag, Piece, Square};
+
+/// Why a SAN move could not be resolved against a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// The text is not SAN at all.
+    Malformed(String),
+    /// Well-formed, but no legal move in the position fits it.
+    Illegal(String),
+    /// More than one legal move fits it; the move needed a file or rank to tell them apart.
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Malformed(san) => write!(f, "'{}' is not a SAN move", san),
+            SanError::Illegal(san) => write!(f, "{} is not legal here", san),
+            SanError::Ambiguous(san) => write!(f, "{} is ambiguous here", san),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Board {
+    /// Resolves a SAN move (`e4`, `Nbd2`, `R1xd5`, `exd6`, `e8=Q+`, `e8Q`, `O-O-O#`) against the legal moves of the position.
+    ///
+    /// Trailing check marks and annotation glyphs (`!`, `?`, `!?`, ...) are accepted but not checked. The
+    /// capture mark is: it must be there exactly when the move captures. Castling may be written with letters or zeros.
+    pub fn parse_san(&self, san: &str) -> Result<ChessMove, SanError> {
+        let malformed = || SanError::Malformed(san.to_string());
+        let text = san.trim().trim_end_matches(['!', '?']);
+        let text = text.strip_suffix(['+', '#']).unwrap_or(text);
+        let legal = self.generate_legal_moves(self.side_to_move);
+
+        let castle = match text {
+            "O-O" | "0-0" => Some(MoveFlag::KingCastle),
+            "O-O-O" | "0-0-0" => Some(MoveFlag::QueenCastle),
+            _ => None,
+        };
+        if let Some(flag) = castle {
+            return legal.into_iter().find(|mv| mv.flag() == flag).ok_or_else(|| SanError::Illegal(san.to_string()));
+        }
+
+        let (piece, mut rest) = match text.as_bytes().first() {
+            Some(b'N' | b'B' | b'R' | b'Q' | b'K') => (Piece::from_char(text.as_bytes()[0] as char).ok_or_else(malformed)?, &text[1..]),
+            Some(_) => (Piece::Pawn, text),
+            None => return Err(malformed()),
+        };
+
+        // Promotion, with or without the `=`
+        let mut promotion = None;
+        if piece == Piece::Pawn {
+            if let Some(last @ ('N' | 'B' | 'R' | 'Q')) = rest.chars().last() {
+                promotion = Piece::from_char(last);
+                rest = &rest[..rest.len() - 1];
+                rest = rest.strip_suffix('=').unwrap_or(rest);
+            }
+        }
+
+        // Target square, then an optional capture mark and disambiguating file and/or rank before it
+        let split = rest.len().checked_sub(2).filter(|&split| rest.is_char_boundary(split)).ok_or_else(malformed)?;
+        let (hint, target) = rest.split_at(split);
+        let to_square = match target.as_bytes() {
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Square::new((rank - b'1') as usize, (file - b'a') as usize),
+            _ => return Err(malformed()),
+        };
+
+        let capture = hint.ends_with('x');
+        let hint = hint.strip_suffix('x').unwrap_or(hint);
+        let well_formed = match (piece, hint.as_bytes()) {
+            (_, []) => !(piece == Piece::Pawn && capture),
+            (Piece::Pawn, [b'a'..=b'h']) => capture, // A pawn only names its file when it captures
+            (Piece::Pawn, _) => false,
+            (_, [b'a'..=b'h' | b'1'..=b'8']) | (_, [b'a'..=b'h', b'1'..=b'8']) => true,
+            _ => false,
+        };
+        if !well_formed {
+            return Err(malformed());
+        }
+
+        let mut candidates = legal.into_iter().filter(|mv| {
+            mv.to() == to_square
+                && mv.promotion() == promotion
+                && mv.is_capture() == capture
+                && !matches!(mv.flag(), MoveFlag::KingCastle | MoveFlag::QueenCastle)
+                && self.get_piece_at_square(mv.from()).map(|(piece, _)| piece) == Some(piece)
+                && mv.from().matches_hint(hint)
+        });
+
+        let mv = candidates.next().ok_or_else(|| SanError::Illegal(san.to_string()))?;
+        match candidates.next() {
+            Some(_) => Err(SanError::Ambiguous(san.to_string())),
+            None => Ok(mv),
+        }
+    }
+
+    /// Writes a legal move in SAN, with only as much disambiguation as the position needs and a check or mate mark.
+    pub fn move_to_san(&self, mv: ChessMove) -> String {
+        let color = self.side_to_move;
+        let mut san = match mv.flag() {
+            MoveFlag::KingCastle => "O-O".to_string(),
+            MoveFlag::QueenCastle => "O-O-O".to_string(),
+            _ => {
+                let piece = self.get_piece_at_square(mv.from()).map_or(Piece::Pawn, |(piece, _)| piece);
+                let mut san = String::new();
+
+                if piece == Piece::Pawn {
+                    if mv.is_capture() {
+                        san.push(mv.from().file() as char);
+                    }
+                } else {
+                    san.push(piece.to_char());
+
+                    // Other pieces of the same kind that could also reach the target square
+                    let rivals: Vec<Square> = self
+                        .generate_legal_moves(color)
+                        .into_iter()
+                        .filter(|other| other.to() == mv.to() && other.from() != mv.from())
+                        .filter(|other| self.get_piece_at_square(other.from()).map(|(piece, _)| piece) == Some(piece))
+                        .map(|other| other.from())
+                        .collect();
+                    if !rivals.is_empty() {
+                        let same_file = rivals.iter().any(|square| square.file() == mv.from().file());
+                        let same_rank = rivals.iter().any(|square| square.rank_ascii() == mv.from().rank_ascii());
+                        if !same_file {
+                            san.push(mv.from().file() as char);
+                        } else if !same_rank {
+                            san.push(mv.from().rank_ascii() as char);
+                        } else {
+                            san.push_str(&mv.from().to_string());
+                        }
+                    }
+                }
+
+                if mv.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(&mv.to().to_string());
+                if let Some(promotion) = mv.promotion() {
+                    san.push('=');
+                    san.push(promotion.to_char());
+                }
+                san
+            }
+        };
+
+        let mut after = *self;
+        after.make_move(mv);
+        let opponent = color.opponent();
+        if GameState::is_in_check(&after, opponent) {
+            san.push(if after.generate_legal_moves(opponent).is_empty() { '#' } else { '+' });
+        }
+        san
+    }
+}
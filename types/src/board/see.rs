@@ -0,0 +1,89 @@
+use crate::board::{Board, Variant};
+use crate::{get_lsb, Bitboard, ChessMove, Color, Piece, Square};
+
+/// What each piece is worth to the exchange, in centipawns and in `Piece` order. The king outweighs
+/// everything else together, so a sequence that ends with it captured never pays.
+pub const SEE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20_000];
+/// The same for Shatranj, where the bishop and queen slots hold the short-ranged alfil and ferz.
+pub const SHATRANJ_SEE_VALUES: [i32; 6] = [100, 320, 120, 500, 180, 20_000];
+
+impl Board {
+    /// Static exchange evaluation: the material the side playing `mv` comes out with once both sides have
+    /// captured on its target square, least valuable piece first, for as long as it pays them to.
+    ///
+    /// Sliders lined up behind a capturer join in as the pieces in front of them leave. Pins and checks
+    /// are not looked at. A quiet move scores what it loses if the piece can be taken where it lands.
+    pub fn see(&self, mv: ChessMove) -> i32 {
+        let (from, to) = (mv.from(), mv.to());
+        let Some((piece, color)) = self.get_piece_at_square(from) else {
+            return 0;
+        };
+        if mv.is_castle() {
+            return 0;
+        }
+        let values = match self.variant {
+            Variant::Standard => &SEE_VALUES,
+            Variant::Shatranj => &SHATRANJ_SEE_VALUES,
+        };
+
+        let mut occupancy = self.occupancy() ^ (1u64 << from.to_index());
+        let mut gains = [0; 32];
+        gains[0] = if mv.is_en_passant() {
+            occupancy ^= 1u64 << Square::new(from.rank_usize(), to.file_usize()).to_index();
+            values[Piece::Pawn as usize]
+        } else {
+            self.get_piece_at_square(to).map_or(0, |(captured, _)| values[captured as usize])
+        };
+        let mut on_square = values[piece as usize]; // The value of the piece standing on `to`
+        if let Some(promotion) = mv.promotion() {
+            gains[0] += values[promotion as usize] - values[Piece::Pawn as usize];
+            on_square = values[promotion as usize];
+        }
+
+        let promotion_rank = (1u64 << to.to_index()) & 0xFF00_0000_0000_00FF != 0;
+        let mut side = color.opponent();
+        let mut depth = 0;
+        while depth + 1 < gains.len() {
+            let Some((capturer, square)) = self.least_valuable_attacker(to, side, occupancy) else {
+                break;
+            };
+
+            depth += 1;
+            gains[depth] = on_square - gains[depth - 1];
+            on_square = values[capturer as usize];
+            if capturer == Piece::Pawn && promotion_rank {
+                gains[depth] += values[Piece::Queen as usize] - values[Piece::Pawn as usize];
+                on_square = values[Piece::Queen as usize];
+            }
+
+            occupancy ^= 1u64 << square;
+            side = side.opponent();
+        }
+
+        // Walk back up: each side only recaptures when that leaves it better off than stopping
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+        gains[0]
+    }
+
+    /// Whether `mv` wins at least `threshold` by static exchange evaluation; `see_ge(mv, 0)` keeps the captures
+    /// that do not lose material.
+    #[inline(always)]
+    pub fn see_ge(&self, mv: ChessMove, threshold: i32) -> bool {
+        self.see(mv) >= threshold
+    }
+
+    /// The cheapest piece of `side` still on the board in `occupancy` that attacks `square`, with where it stands.
+    #[inline(always)]
+    fn least_valuable_attacker(&self, square: Square, side: Color, occupancy: Bitboard) -> Option<(Piece, usize)> {
+        let attackers = self.attackers_to(square, occupancy) & occupancy & self.occupancy[side as usize];
+        [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King]
+            .into_iter()
+            .find_map(|piece| {
+                let pieces = attackers & self.pieces[self.get_piece_index(piece, side)];
+                (pieces != 0).then(|| (piece, get_lsb(pieces)))
+            })
+    }
+}
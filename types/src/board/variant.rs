@@ -0,0 +1,99 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::board::{Board, FenError};
+use crate::{Color, Piece};
+
+/// The starting position of Shatranj. The kings do not face each other: Black's stands on d8.
+pub const SHATRANJ_FEN: &str = "rnbkqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1";
+
+/// Which rules a board is played under.
+///
+/// Shatranj keeps the board and most pieces of modern chess, so it reuses their slots: the bishop slot
+/// holds the alfil, which leaps two squares diagonally, and the queen slot the ferz, which steps one
+/// square diagonally. Both keep the `B` and `Q` letters in FEN, SAN and UCI promotions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Pawns only ever step one square and promote to a ferz. There is no castling, stalemating the
+    /// opponent wins, and so does capturing everything but their king.
+    Shatranj,
+}
+
+impl Variant {
+    /// The pieces a pawn may promote to, the most valuable first.
+    #[inline(always)]
+    pub fn promotion_pieces(self) -> &'static [Piece] {
+        match self {
+            Variant::Standard => &[Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight],
+            Variant::Shatranj => &[Piece::Queen],
+        }
+    }
+
+    /// Whether the side left without a legal move, but not in check, loses rather than draws.
+    #[inline(always)]
+    pub fn stalemate_loses(self) -> bool {
+        self == Variant::Shatranj
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "standard"),
+            Variant::Shatranj => write!(f, "shatranj"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "standard" | "chess" => Ok(Variant::Standard),
+            "shatranj" => Ok(Variant::Shatranj),
+            other => Err(format!("Unknown variant '{}' (expected standard or shatranj)", other)),
+        }
+    }
+}
+
+impl Board {
+    /// The starting position of the given variant.
+    pub fn new_variant(variant: Variant) -> Board {
+        match variant {
+            Variant::Standard => Board::new(),
+            Variant::Shatranj => Board::from_fen_variant(SHATRANJ_FEN, Variant::Shatranj).expect("Valid Shatranj FEN"),
+        }
+    }
+
+    /// The rules this board is played under.
+    #[inline(always)]
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Whether `color` is down to a bare king.
+    #[inline(always)]
+    pub fn has_bare_king(&self, color: Color) -> bool {
+        self.occupancy[color as usize] == self.pieces[self.get_piece_index(Piece::King, color)]
+    }
+
+    /// Loads a FEN under the given rules. A Shatranj position has no castling rights or en passant square to give.
+    pub fn from_fen_variant(fen: &str, variant: Variant) -> Result<Board, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if variant == Variant::Shatranj {
+            if fields.get(2).is_some_and(|&field| field != "-") {
+                return Err(FenError::BadCastling(fields[2].to_string()));
+            }
+            if fields.get(3).is_some_and(|&field| field != "-") {
+                return Err(FenError::BadEnPassant(fields[3].to_string()));
+            }
+        }
+
+        let mut board = Board::from_fen(fen)?;
+        board.variant = variant;
+        board.checkers = board.find_checkers(); // The alfil and ferz attack differently from the bishop and queen
+        Ok(board)
+    }
+}
@@ -1,9 +1,14 @@
+use std::sync::OnceLock;
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
+use crate::{Color, Piece, Square};
+
+/// The key table every `Board` hashes with, created on first use.
+static KEYS: OnceLock<Zobrist> = OnceLock::new();
 
 pub struct Zobrist {
-    pub(crate) piece_keys: [[[u64; 64]; 12]; 2], // [color][piece_type][square]
+    pub(crate) piece_keys: [[[u64; 64]; 6]; 2], // [color][piece_type][square]
     pub(crate) castling_keys: [u64; 16],         // Castling rights (4 bits: KQkq)
     pub(crate) en_passant_keys: [u64; 8],        // En passant file (8 possible files)
     pub(crate) side_to_move_key: u64,            // Side to move
@@ -18,28 +23,28 @@ impl Default for Zobrist {
 impl Zobrist {
     pub fn new() -> Self {
         let mut rng = StdRng::seed_from_u64(0); // Use a fixed seed for reproducibility
-        let mut piece_keys = [[[0u64; 64]; 12]; 2];
+        let mut piece_keys = [[[0u64; 64]; 6]; 2];
         let mut castling_keys = [0u64; 16];
         let mut en_passant_keys = [0u64; 8];
         let side_to_move_key = rng.random();
 
         // Initialize piece keys
-        for color in 0..2 {
-            for piece in 0..12 {
-                for square in 0..64 {
-                    piece_keys[color][piece][square] = rng.random();
+        for color_keys in piece_keys.iter_mut() {
+            for square_keys in color_keys.iter_mut() {
+                for key in square_keys.iter_mut() {
+                    *key = rng.random();
                 }
             }
         }
 
         // Initialize castling keys
-        for i in 0..16 {
-            castling_keys[i] = rng.random();
+        for key in castling_keys.iter_mut() {
+            *key = rng.random();
         }
 
         // Initialize en passant keys
-        for i in 0..8 {
-            en_passant_keys[i] = rng.random();
+        for key in en_passant_keys.iter_mut() {
+            *key = rng.random();
         }
 
         Self {
@@ -49,4 +54,20 @@ impl Zobrist {
             side_to_move_key,
         }
     }
+
+    /// The shared key table behind `Board::hash`; built once, so callers never need their own `Zobrist`.
+    #[inline(always)]
+    pub fn global() -> &'static Zobrist {
+        KEYS.get_or_init(Zobrist::new)
+    }
+
+    #[inline(always)]
+    pub(crate) fn piece_key(&self, piece: Piece, color: Color, square: Square) -> u64 {
+        self.piece_keys[color as usize][piece as usize][square.to_index()]
+    }
+
+    #[inline(always)]
+    pub(crate) fn en_passant_key(&self, en_passant: Option<Square>) -> u64 {
+        en_passant.map_or(0, |square| self.en_passant_keys[square.file_usize()])
+    }
 }
@@ -1,3 +1,243 @@
-use crate::Square;
+use std::fmt;
+use crate::{Piece, Square};
 
-pub type ChessMove = (Square, Square);
\ No newline at end of file
+/// Describes what kind of move a `ChessMove` is, stored in the upper 4 bits of the encoding.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MoveFlag {
+    Quiet = 0,
+    DoublePawnPush = 1,
+    KingCastle = 2,
+    QueenCastle = 3,
+    Capture = 4,
+    EnPassant = 5,
+    KnightPromotion = 8,
+    BishopPromotion = 9,
+    RookPromotion = 10,
+    QueenPromotion = 11,
+    KnightPromotionCapture = 12,
+    BishopPromotionCapture = 13,
+    RookPromotionCapture = 14,
+    QueenPromotionCapture = 15,
+}
+
+const CAPTURE_BIT: u8 = 0b0100;
+const PROMOTION_BIT: u8 = 0b1000;
+
+impl MoveFlag {
+    #[inline(always)]
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(MoveFlag::Quiet),
+            1 => Some(MoveFlag::DoublePawnPush),
+            2 => Some(MoveFlag::KingCastle),
+            3 => Some(MoveFlag::QueenCastle),
+            4 => Some(MoveFlag::Capture),
+            5 => Some(MoveFlag::EnPassant),
+            8 => Some(MoveFlag::KnightPromotion),
+            9 => Some(MoveFlag::BishopPromotion),
+            10 => Some(MoveFlag::RookPromotion),
+            11 => Some(MoveFlag::QueenPromotion),
+            12 => Some(MoveFlag::KnightPromotionCapture),
+            13 => Some(MoveFlag::BishopPromotionCapture),
+            14 => Some(MoveFlag::RookPromotionCapture),
+            15 => Some(MoveFlag::QueenPromotionCapture),
+            _ => None,
+        }
+    }
+
+    /// Returns the promotion flag for the given piece, with or without a capture.
+    #[inline(always)]
+    pub fn promotion(piece: Piece, capture: bool) -> Self {
+        let piece_bits = match piece {
+            Piece::Knight => 0,
+            Piece::Bishop => 1,
+            Piece::Rook => 2,
+            _ => 3, // Anything else is treated as a queen promotion
+        };
+        let capture_bits = if capture { CAPTURE_BIT } else { 0 };
+        Self::from_u8(PROMOTION_BIT | capture_bits | piece_bits).expect("Promotion flags are always valid")
+    }
+}
+
+/// A move packed into 16 bits: origin in bits 0-5, destination in bits 6-11 and a `MoveFlag` in bits 12-15.
+///
+/// Castling is stored as the king's own two-square move (e.g. e1g1), just like UCI.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ChessMove(u16);
+
+impl ChessMove {
+    /// The all-zero move (a1a1), used as "no move" in packed storage.
+    pub const NULL: ChessMove = ChessMove(0);
+
+    #[inline(always)]
+    pub fn new(from: Square, to: Square, flag: MoveFlag) -> Self {
+        ChessMove(from.to_index() as u16 | (to.to_index() as u16) << 6 | (flag as u16) << 12)
+    }
+
+    /// Rebuilds a move from its 16-bit encoding, returning `None` for an unknown flag nibble.
+    #[inline(always)]
+    pub fn from_u16(value: u16) -> Option<Self> {
+        MoveFlag::from_u8((value >> 12) as u8).map(|_| ChessMove(value))
+    }
+
+    #[inline(always)]
+    pub fn to_u16(self) -> u16 {
+        self.0
+    }
+
+    #[inline(always)]
+    pub fn from(self) -> Square {
+        Square::from_index((self.0 & 0x3F) as usize)
+    }
+
+    #[inline(always)]
+    pub fn to(self) -> Square {
+        Square::from_index(((self.0 >> 6) & 0x3F) as usize)
+    }
+
+    #[inline(always)]
+    pub fn flag(self) -> MoveFlag {
+        MoveFlag::from_u8((self.0 >> 12) as u8).expect("ChessMove always holds a valid flag")
+    }
+
+    #[inline(always)]
+    fn flag_bits(self) -> u8 {
+        (self.0 >> 12) as u8
+    }
+
+    /// Returns the piece a pawn promotes to, if this is a promotion.
+    #[inline(always)]
+    pub fn promotion(self) -> Option<Piece> {
+        if !self.is_promotion() {
+            return None;
+        }
+        match self.flag_bits() & 0b11 {
+            0 => Some(Piece::Knight),
+            1 => Some(Piece::Bishop),
+            2 => Some(Piece::Rook),
+            _ => Some(Piece::Queen),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_capture(self) -> bool {
+        self.flag_bits() & CAPTURE_BIT != 0
+    }
+
+    #[inline(always)]
+    pub fn is_promotion(self) -> bool {
+        self.flag_bits() & PROMOTION_BIT != 0
+    }
+
+    #[inline(always)]
+    pub fn is_castle(self) -> bool {
+        matches!(self.flag(), MoveFlag::KingCastle | MoveFlag::QueenCastle)
+    }
+
+    #[inline(always)]
+    pub fn is_en_passant(self) -> bool {
+        self.flag() == MoveFlag::EnPassant
+    }
+
+    #[inline(always)]
+    pub fn is_double_pawn_push(self) -> bool {
+        self.flag() == MoveFlag::DoublePawnPush
+    }
+
+    /// Returns true if the move neither captures nor promotes.
+    #[inline(always)]
+    pub fn is_quiet(self) -> bool {
+        !self.is_capture() && !self.is_promotion()
+    }
+
+    /// Encodes the move in Polyglot book format.
+    ///
+    /// Polyglot stores castling as "king takes own rook" (e1h1, e1a1), so castles are translated here.
+    pub fn to_polyglot(self) -> u16 {
+        let from = self.from().to_index() as u16;
+        let mut to = self.to().to_index() as u16;
+
+        match self.flag() {
+            MoveFlag::KingCastle => to = from - from % 8 + 7,
+            MoveFlag::QueenCastle => to = from - from % 8,
+            _ => {}
+        }
+
+        let promotion = match self.promotion() {
+            Some(Piece::Knight) => 1,
+            Some(Piece::Bishop) => 2,
+            Some(Piece::Rook) => 3,
+            Some(Piece::Queen) => 4,
+            _ => 0,
+        };
+
+        to | from << 6 | promotion << 12
+    }
+}
+
+impl fmt::Display for ChessMove {
+    /// Formats the move in UCI long algebraic notation (e.g. `e2e4`, `e7e8n`).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from(), self.to())?;
+        if let Some(piece) = self.promotion() {
+            write!(f, "{}", piece.to_char().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ChessMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?})", self, self.flag())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_encoding() {
+        let mv = ChessMove::new(Square::B7, Square::A8, MoveFlag::promotion(Piece::Knight, true));
+
+        assert_eq!(mv.from(), Square::B7);
+        assert_eq!(mv.to(), Square::A8);
+        assert_eq!(mv.flag(), MoveFlag::KnightPromotionCapture);
+        assert_eq!(mv.promotion(), Some(Piece::Knight));
+        assert!(mv.is_capture());
+        assert_eq!(ChessMove::from_u16(mv.to_u16()), Some(mv));
+        assert_eq!(mv.to_string(), "b7a8n");
+    }
+
+    #[test]
+    fn test_flags() {
+        let push = ChessMove::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        assert!(push.is_double_pawn_push());
+        assert!(push.is_quiet());
+        assert_eq!(push.promotion(), None);
+
+        let ep = ChessMove::new(Square::E5, Square::D6, MoveFlag::EnPassant);
+        assert!(ep.is_en_passant());
+        assert!(ep.is_capture());
+
+        let castle = ChessMove::new(Square::E8, Square::C8, MoveFlag::QueenCastle);
+        assert!(castle.is_castle());
+        assert!(!castle.is_capture());
+    }
+
+    #[test]
+    fn test_polyglot_encoding() {
+        let e2e4 = ChessMove::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        assert_eq!(e2e4.to_polyglot(), 28 | 12 << 6);
+
+        // Castling is king-takes-rook in Polyglot
+        let short = ChessMove::new(Square::E1, Square::G1, MoveFlag::KingCastle);
+        assert_eq!(short.to_polyglot(), Square::H1.to_index() as u16 | (Square::E1.to_index() as u16) << 6);
+        let long = ChessMove::new(Square::E8, Square::C8, MoveFlag::QueenCastle);
+        assert_eq!(long.to_polyglot(), Square::A8.to_index() as u16 | (Square::E8.to_index() as u16) << 6);
+
+        let under = ChessMove::new(Square::A7, Square::A8, MoveFlag::RookPromotion);
+        assert_eq!(under.to_polyglot() >> 12, 3);
+    }
+}
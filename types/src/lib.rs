@@ -5,9 +5,13 @@ pub mod board;
 pub mod chess_move;
 
 pub use square::Square;
-pub use chess_move::ChessMove;
+pub use chess_move::{ChessMove, MoveFlag};
 pub use piece::{Color, Piece};
 pub use bitboard::{clear_bit, count_bits, get_lsb, is_bit_set, set_bit, Bitboard, FULL_BOARD};
-pub use board::Board;
+pub use board::{
+    Board, Epd, EpdError, FenError, SanError, Undo, Variant, ALFIL_ATTACKS, FERZ_ATTACKS, KING_ATTACKS, KNIGHT_ATTACKS, PAWN_ATTACKS,
+    SEE_VALUES, SHATRANJ_FEN, SHATRANJ_SEE_VALUES,
+};
 pub use board::zobrist::Zobrist;
-pub use board::game_state::GameState;
\ No newline at end of file
+pub use board::game_state::{GameOutcome, GameState};
+pub use board::pgn::{DatabaseError, Game, GameMove, Line, PgnDatabase, PgnError};
\ No newline at end of file
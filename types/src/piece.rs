@@ -29,6 +29,18 @@ impl Piece {
             _ => None,
         }
     }
+
+    /// Returns the uppercase letter used for this piece in FEN and SAN.
+    pub fn to_char(self) -> char {
+        match self {
+            Piece::Pawn => 'P',
+            Piece::Knight => 'N',
+            Piece::Bishop => 'B',
+            Piece::Rook => 'R',
+            Piece::Queen => 'Q',
+            Piece::King => 'K',
+        }
+    }
 }
 
 #[repr(u8)]
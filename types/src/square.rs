@@ -1,3 +1,4 @@
+use std::fmt;
 use std::str::FromStr;
 
 #[repr(u8)]
@@ -39,6 +40,11 @@ impl Square {
     pub fn rank_usize(self) -> usize {
         self.to_index() / 8
     }
+
+    #[inline(always)]
+    pub fn file_usize(self) -> usize {
+        self.to_index() % 8
+    }
     
      #[inline(always)]
     pub fn matches_hint(&self, hint: &str) -> bool {
@@ -49,11 +55,11 @@ impl Square {
             1 => {
                 // Hint could be either a file or a rank
                 let hint_char = hint_bytes[0];
-                self.file() == hint_char || self.rank_usize() == hint_char as usize
+                self.file() == hint_char || self.rank_ascii() == hint_char
             }
             2 => {
                 // Hint is both a file and a rank
-                self.file() == hint_bytes[0] && self.rank_usize() == hint_bytes[1] as usize
+                self.file() == hint_bytes[0] && self.rank_ascii() == hint_bytes[1]
             }
             _ => false, // Invalid hint
         }
@@ -81,8 +87,8 @@ impl FromStr for Square {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.len() == 2 {
             let bytes = s.as_bytes();
-            let file = (bytes[0] - b'a') as usize; // Convert 'a'-'h' to 0-7
-            let rank = (bytes[1] - b'1') as usize; // Convert '1'-'8' to 0-7
+            let file = bytes[0].wrapping_sub(b'a') as usize; // Convert 'a'-'h' to 0-7
+            let rank = bytes[1].wrapping_sub(b'1') as usize; // Convert '1'-'8' to 0-7
 
             if file < 8 && rank < 8 {
                 return Ok(Square::new(rank, file));
@@ -90,4 +96,10 @@ impl FromStr for Square {
         }
         Err(())
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.file() as char, self.rank_ascii() as char)
+    }
+}